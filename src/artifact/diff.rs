@@ -0,0 +1,344 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::artifact::internal_artifact::InternalArtifact;
+
+// sub stat values read back from a file are rounded, allow for that when comparing
+const VALUE_EPS: f64 = 1e-3;
+
+// the same artifact in both scans
+pub struct Pair<'a> {
+    pub old: &'a InternalArtifact,
+    pub new: &'a InternalArtifact,
+}
+
+// Changes from one scan of the inventory to a later one
+pub struct InventoryDiff<'a> {
+    pub added: Vec<&'a InternalArtifact>,
+    // fed to other artifacts or destroyed
+    pub removed: Vec<&'a InternalArtifact>,
+    pub upgraded: Vec<Pair<'a>>,
+    pub unchanged: Vec<Pair<'a>>,
+}
+
+// Whether `new` can be `old` after some upgrades: same set, slot, rarity and main stat, no
+// lower level, and every sub stat of `old` still there with at least the same value. Main
+// stat values and equip are not compared, some formats do not keep them.
+pub fn can_upgrade_to(old: &InternalArtifact, new: &InternalArtifact) -> bool {
+    if old.set_name != new.set_name
+        || old.slot != new.slot
+        || old.star != new.star
+        || old.main_stat.name != new.main_stat.name
+        || old.level > new.level
+    {
+        return false;
+    }
+
+    old.sub_stats().all(|o| {
+        new.sub_stats()
+            .any(|n| n.name == o.name && n.value + VALUE_EPS >= o.value)
+    })
+}
+
+fn has_same_stats(old: &InternalArtifact, new: &InternalArtifact) -> bool {
+    old.level == new.level
+        && old.sub_stats().count() == new.sub_stats().count()
+        && old.sub_stats().zip(new.sub_stats()).all(|(o, n)| {
+            o.name == n.name && (o.value - n.value).abs() < VALUE_EPS
+        })
+}
+
+// how far apart a compatible pair is, smaller is a more likely match
+fn upgrade_cost(old: &InternalArtifact, new: &InternalArtifact) -> f64 {
+    let level = (new.level - old.level) as f64;
+    let added = new.sub_stats().count() as f64 - old.sub_stats().count() as f64;
+    level * 100.0 + added
+}
+
+pub fn diff<'a>(old: &'a [InternalArtifact], new: &'a [InternalArtifact]) -> InventoryDiff<'a> {
+    let mut upgraded = Vec::new();
+    let mut unchanged = Vec::new();
+    let mut push_pair = |o: &'a InternalArtifact, n: &'a InternalArtifact| {
+        // e.g. only the equip changed
+        if has_same_stats(o, n) {
+            unchanged.push(Pair { old: o, new: n });
+        } else {
+            upgraded.push(Pair { old: o, new: n });
+        }
+    };
+
+    // artifacts that kept their id, then identical ones. An id only counts if the stats agree,
+    // ids made from the content can move between artifacts that look alike.
+    let mut by_id: HashMap<&str, &InternalArtifact> = old
+        .iter()
+        .filter_map(|a| a.id.as_deref().map(|id| (id, a)))
+        .collect();
+    let mut matched_old: HashSet<*const InternalArtifact> = HashSet::new();
+    let mut new_left: Vec<&InternalArtifact> = Vec::new();
+    for a in new.iter() {
+        match a.id.as_deref().and_then(|id| by_id.remove(id)) {
+            Some(o) if can_upgrade_to(o, a) => {
+                matched_old.insert(o);
+                push_pair(o, a);
+            }
+            _ => new_left.push(a),
+        }
+    }
+    let mut same: HashMap<&InternalArtifact, Vec<&InternalArtifact>> = HashMap::new();
+    for a in old.iter().rev() {
+        if !matched_old.contains(&(a as *const InternalArtifact)) {
+            same.entry(a).or_insert_with(Vec::new).push(a);
+        }
+    }
+    let mut rest: Vec<&InternalArtifact> = Vec::new();
+    for a in new_left {
+        match same.get_mut(a).and_then(|v| v.pop()) {
+            Some(o) => push_pair(o, a),
+            None => rest.push(a),
+        }
+    }
+    let new_left = rest;
+    let remaining: HashSet<*const InternalArtifact> = same
+        .into_values()
+        .flatten()
+        .map(|a| a as *const InternalArtifact)
+        .collect();
+    let mut old_left: Vec<&InternalArtifact> = old
+        .iter()
+        .filter(|a| remaining.contains(&(*a as *const InternalArtifact)))
+        .collect();
+
+    // then the closest compatible pairs, highest levels first so a +20 is not matched to
+    // what used to be a +0 when its own old version is also around
+    old_left.sort_by(|a, b| b.level.cmp(&a.level));
+    let mut taken = vec![false; new_left.len()];
+    let mut removed = Vec::new();
+    for o in old_left {
+        let best = new_left
+            .iter()
+            .enumerate()
+            .filter(|(i, n)| !taken[*i] && can_upgrade_to(o, n))
+            .min_by(|(_, a), (_, b)| {
+                upgrade_cost(o, a)
+                    .partial_cmp(&upgrade_cost(o, b))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .map(|(i, _)| i);
+        match best {
+            Some(i) => {
+                taken[i] = true;
+                push_pair(o, new_left[i]);
+            }
+            None => removed.push(o),
+        }
+    }
+    let added = new_left
+        .into_iter()
+        .zip(taken.into_iter())
+        .filter(|(_, t)| !t)
+        .map(|(a, _)| a)
+        .collect();
+
+    InventoryDiff {
+        added,
+        removed,
+        upgraded,
+        unchanged,
+    }
+}
+
+// An earlier export, to tell whether a scanned artifact was already there. Only the same
+// artifact counts, possibly re-equipped: a new drop can look like an upgrade of an old one,
+// and taking it for known would end an incremental scan too early.
+pub struct KnownArtifacts {
+    exact: HashSet<InternalArtifact>,
+}
+
+// Leaves out the equip, and the main stat value, which GOOD and MingyuLab read back as 0. The
+// value follows from the rarity, level and main stat, so nothing is lost.
+fn known_key(artifact: &InternalArtifact) -> InternalArtifact {
+    let mut ans = artifact.clone();
+    ans.equip = None;
+    ans.main_stat.value = 0.0;
+    ans
+}
+
+impl KnownArtifacts {
+    pub fn new(artifacts: &[InternalArtifact]) -> KnownArtifacts {
+        KnownArtifacts {
+            exact: artifacts.iter().map(known_key).collect(),
+        }
+    }
+
+    pub fn contains(&self, artifact: &InternalArtifact) -> bool {
+        self.exact.contains(&known_key(artifact))
+    }
+}
+
+// `scanned`, followed by the artifacts of `old` that were not scanned again. Artifacts found
+// in `old` keep their id there.
+pub fn merge(old: &[InternalArtifact], scanned: Vec<InternalArtifact>) -> Vec<InternalArtifact> {
+    let d = diff(old, &scanned);
+    let ids: Vec<(usize, Option<String>)> = d
+        .unchanged
+        .iter()
+        .chain(d.upgraded.iter())
+        .filter(|p| p.old.id.is_some())
+        .map(|p| {
+            let index = scanned
+                .iter()
+                .position(|a| std::ptr::eq(a, p.new))
+                .unwrap();
+            (index, p.old.id.clone())
+        })
+        .collect();
+    let rest: Vec<InternalArtifact> = d.removed.into_iter().cloned().collect();
+
+    let mut ans = scanned;
+    for (index, id) in ids {
+        ans[index].id = id;
+    }
+    ans.extend(rest);
+    ans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::internal_artifact::test_util::artifact;
+    use crate::artifact::internal_artifact::{ArtifactSetName, ArtifactStatName::*};
+    use crate::expo::exporter;
+
+    fn crit(level: u32, crit_rate: f64) -> InternalArtifact {
+        artifact(level, &[(Critical, crit_rate), (Atk, 19.0), (Def, 23.0)])
+    }
+
+    #[test]
+    fn same_inventory_is_unchanged() {
+        let old = vec![crit(0, 0.039), crit(20, 0.14)];
+        let new = old.clone();
+        let d = diff(&old, &new);
+        assert_eq!(d.unchanged.len(), 2);
+        assert!(d.added.is_empty() && d.removed.is_empty() && d.upgraded.is_empty());
+    }
+
+    #[test]
+    fn equip_change_is_unchanged() {
+        let old = vec![crit(20, 0.14)];
+        let mut new = old.clone();
+        new[0].equip = Some(String::from("胡桃"));
+        let d = diff(&old, &new);
+        assert_eq!(d.unchanged.len(), 1);
+    }
+
+    #[test]
+    fn upgrade_is_found() {
+        let old = vec![crit(0, 0.039)];
+        let new = vec![artifact(
+            4,
+            &[(Critical, 0.078), (Atk, 19.0), (Def, 23.0), (Hp, 209.0)],
+        )];
+        let d = diff(&old, &new);
+        assert_eq!(d.upgraded.len(), 1);
+        assert!(d.added.is_empty() && d.removed.is_empty());
+    }
+
+    #[test]
+    fn different_artifacts_are_added_and_removed() {
+        let old = vec![crit(0, 0.039)];
+        let mut other = crit(0, 0.039);
+        other.set_name = ArtifactSetName::EmblemOfSeveredFate;
+        let new = vec![other];
+        let d = diff(&old, &new);
+        assert_eq!(d.added.len(), 1);
+        assert_eq!(d.removed.len(), 1);
+    }
+
+    #[test]
+    fn lower_stats_are_not_an_upgrade() {
+        let old = vec![crit(8, 0.078)];
+        let new = vec![crit(12, 0.06)];
+        let d = diff(&old, &new);
+        assert_eq!(d.added.len(), 1);
+        assert_eq!(d.removed.len(), 1);
+    }
+
+    #[test]
+    fn higher_levels_are_matched_first() {
+        // the +0 could become either, the +8 only the +12
+        let old = vec![crit(0, 0.039), crit(8, 0.078)];
+        let new = vec![crit(12, 0.1), crit(16, 0.06)];
+        let d = diff(&old, &new);
+        assert_eq!(d.upgraded.len(), 2);
+        assert!(d.removed.is_empty() && d.added.is_empty());
+        let pair = d.upgraded.iter().find(|p| p.old.level == 8).unwrap();
+        assert_eq!(pair.new.level, 12);
+    }
+
+    #[test]
+    fn known_artifacts_ignore_equip() {
+        let known = KnownArtifacts::new(&[crit(20, 0.14)]);
+        let mut scanned = crit(20, 0.14);
+        scanned.equip = Some(String::from("胡桃"));
+        assert!(known.contains(&scanned));
+    }
+
+    #[test]
+    fn known_artifacts_do_not_include_upgrades() {
+        let known = KnownArtifacts::new(&[crit(0, 0.039)]);
+        assert!(!known.contains(&crit(4, 0.078)));
+    }
+
+    #[test]
+    fn known_artifacts_from_formats_without_main_stat_values() {
+        let scanned = vec![crit(20, 0.14), crit(4, 0.078)];
+        for name in ["good", "mingyulab"].iter() {
+            let exporter = exporter::find(name).unwrap();
+            let mut buf: Vec<u8> = Vec::new();
+            exporter.write(&scanned, &mut buf).unwrap();
+            let previous = exporter.read(&String::from_utf8(buf).unwrap()).unwrap();
+            assert_eq!(previous[0].main_stat.value, 0.0);
+
+            let known = KnownArtifacts::new(&previous);
+            assert!(scanned.iter().all(|a| known.contains(a)), "{}", name);
+            assert!(!known.contains(&crit(8, 0.078)));
+        }
+    }
+
+    #[test]
+    fn merge_keeps_old_ids_and_unscanned_artifacts() {
+        let mut kept = crit(0, 0.039);
+        kept.id = Some(String::from("kept"));
+        let mut other = crit(0, 0.039);
+        other.set_name = ArtifactSetName::EmblemOfSeveredFate;
+        other.id = Some(String::from("other"));
+        let old = vec![kept, other];
+
+        let scanned = vec![crit(20, 0.14), crit(4, 0.078)];
+        let merged = merge(&old, scanned);
+        assert_eq!(merged.len(), 3);
+        // the new +20 has no old version, the +4 is the old +0
+        assert_eq!(merged[0].id, None);
+        assert_eq!(merged[1].id.as_deref(), Some("kept"));
+        assert_eq!(merged[2].id.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn id_match_needs_compatible_stats() {
+        let mut old = crit(8, 0.078);
+        old.id = Some(String::from("a"));
+        let mut swapped = crit(0, 0.039);
+        swapped.id = Some(String::from("a"));
+        let mut upgraded = crit(12, 0.1);
+        upgraded.id = Some(String::from("b"));
+
+        let old = vec![old];
+        let new = vec![swapped, upgraded];
+        let d = diff(&old, &new);
+        assert_eq!(d.upgraded.len(), 1);
+        assert_eq!(d.upgraded[0].new.level, 12);
+        assert_eq!(d.added.len(), 1);
+        assert_eq!(d.added[0].level, 0);
+    }
+}
+
@@ -1,10 +1,11 @@
 use regex::Regex;
+use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 use edit_distance;
 use log::error;
-use strum_macros::Display;
+use strum_macros::{Display, EnumIter};
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, EnumIter)]
 pub enum ArtifactStatName {
     HealingBonus,
     CriticalDamage,
@@ -27,7 +28,7 @@ pub enum ArtifactStatName {
     DendroBonus,
 }
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Hash, Clone, PartialEq, Eq, EnumIter)]
 pub enum ArtifactSlot {
     Flower,
     Feather,
@@ -37,7 +38,7 @@ pub enum ArtifactSlot {
 }
 
 #[derive(Debug, Hash, Clone, PartialEq, Eq)]
-#[derive(Display)]
+#[derive(Display, EnumIter)]
 pub enum ArtifactSetName {
     ArchaicPetra,
     HeartOfDepth,
@@ -92,7 +93,7 @@ pub struct ArtifactStat {
     pub value: f64,
 }
 
-#[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct InternalArtifact {
     pub set_name: ArtifactSetName,
     pub slot: ArtifactSlot,
@@ -104,6 +105,96 @@ pub struct InternalArtifact {
     pub sub_stat_3: Option<ArtifactStat>,
     pub sub_stat_4: Option<ArtifactStat>,
     pub equip: Option<String>,
+    // stable across exports, see `assign_ids`. Not part of equality or hashing
+    pub id: Option<String>,
+}
+
+impl Hash for InternalArtifact {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.set_name.hash(state);
+        self.slot.hash(state);
+        self.star.hash(state);
+        self.level.hash(state);
+        self.main_stat.hash(state);
+        self.sub_stat_1.hash(state);
+        self.sub_stat_2.hash(state);
+        self.sub_stat_3.hash(state);
+        self.sub_stat_4.hash(state);
+        self.equip.hash(state);
+    }
+}
+
+impl PartialEq for InternalArtifact {
+    fn eq(&self, other: &Self) -> bool {
+        self.set_name == other.set_name
+            && self.slot == other.slot
+            && self.star == other.star
+            && self.level == other.level
+            && self.main_stat == other.main_stat
+            && self.sub_stat_1 == other.sub_stat_1
+            && self.sub_stat_2 == other.sub_stat_2
+            && self.sub_stat_3 == other.sub_stat_3
+            && self.sub_stat_4 == other.sub_stat_4
+            && self.equip == other.equip
+    }
+}
+
+impl Eq for InternalArtifact {}
+
+// 64 bit FNV-1a, unlike `DefaultHasher` it gives the same result with every build
+fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in data {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+impl InternalArtifact {
+    // What the artifact is, for ids, from what upgrades do not change: the set, slot, rarity,
+    // main stat and the first three sub stats, a fourth one can be added by an upgrade. Equip
+    // and values are left out.
+    fn content_key(&self) -> String {
+        let mut key = format!(
+            "{}/{:?}/{}/{:?}",
+            self.set_name, self.slot, self.star, self.main_stat.name
+        );
+        for stat in self.sub_stats().take(3) {
+            key += &format!("/{:?}", stat.name);
+        }
+        key
+    }
+
+    // the sub stats that are present, in order
+    pub fn sub_stats(&self) -> impl Iterator<Item = &ArtifactStat> {
+        self.sub_stat_1
+            .iter()
+            .chain(self.sub_stat_2.iter())
+            .chain(self.sub_stat_3.iter())
+            .chain(self.sub_stat_4.iter())
+    }
+}
+
+// Gives every artifact without an id one made from its content, so exporting the same
+// inventory again, upgraded or not, gives the same ids. Artifacts with the same key are told
+// apart by their order in `artifacts`, i.e. their grid position, so their ids can swap when
+// that order changes.
+pub fn assign_ids(artifacts: &mut [InternalArtifact]) {
+    let mut used: HashSet<String> = artifacts.iter().filter_map(|a| a.id.clone()).collect();
+    for artifact in artifacts.iter_mut().filter(|a| a.id.is_none()) {
+        let key = artifact.content_key();
+        let mut n = 0;
+        let id = loop {
+            let id = format!("{:016x}", fnv1a(format!("{}#{}", key, n).as_bytes()));
+            if !used.contains(&id) {
+                break id;
+            }
+            n += 1;
+        };
+        used.insert(id.clone());
+        artifact.id = Some(id);
+    }
 }
 
 impl Hash for ArtifactStat {
@@ -153,6 +244,39 @@ impl ArtifactStatName {
     }
 }
 
+impl ArtifactStatName {
+    pub fn to_zh_cn(&self) -> &'static str {
+        match self {
+            ArtifactStatName::HealingBonus => "治疗加成",
+            ArtifactStatName::CriticalDamage => "暴击伤害",
+            ArtifactStatName::Critical => "暴击率",
+            ArtifactStatName::Atk | ArtifactStatName::AtkPercentage => "攻击力",
+            ArtifactStatName::ElementalMastery => "元素精通",
+            ArtifactStatName::Recharge => "元素充能效率",
+            ArtifactStatName::Hp | ArtifactStatName::HpPercentage => "生命值",
+            ArtifactStatName::Def | ArtifactStatName::DefPercentage => "防御力",
+            ArtifactStatName::ElectroBonus => "雷元素伤害加成",
+            ArtifactStatName::PyroBonus => "火元素伤害加成",
+            ArtifactStatName::HydroBonus => "水元素伤害加成",
+            ArtifactStatName::CryoBonus => "冰元素伤害加成",
+            ArtifactStatName::AnemoBonus => "风元素伤害加成",
+            ArtifactStatName::GeoBonus => "岩元素伤害加成",
+            ArtifactStatName::DendroBonus => "草元素伤害加成",
+            ArtifactStatName::PhysicalBonus => "物理伤害加成",
+        }
+    }
+
+    pub fn is_percentage(&self) -> bool {
+        match self {
+            ArtifactStatName::Atk
+            | ArtifactStatName::ElementalMastery
+            | ArtifactStatName::Hp
+            | ArtifactStatName::Def => false,
+            _ => true,
+        }
+    }
+}
+
 impl ArtifactStat {
     // e.g "生命值+4,123", "暴击率+10%"
     pub fn from_zh_cn_raw(s: &str) -> Option<ArtifactStat> {
@@ -184,53 +308,110 @@ impl ArtifactStat {
             value,
         })
     }
+
+    // Values are kept as fractions for percentages (0.105 for 10.5%). Formats that write
+    // percent numbers go through these two.
+    pub fn display_value(&self) -> f64 {
+        if self.name.is_percentage() {
+            self.value * 100.0
+        } else {
+            self.value
+        }
+    }
+
+    pub fn from_display_value(name: ArtifactStatName, value: f64) -> ArtifactStat {
+        let value = if name.is_percentage() {
+            value / 100.0
+        } else {
+            value
+        };
+        ArtifactStat { name, value }
+    }
+
+    // the value as the game displays it, e.g "4,123", "10.5%"
+    pub fn to_zh_cn_value(&self) -> String {
+        if self.name.is_percentage() {
+            return format!("{:.1}%", self.value * 100.0);
+        }
+
+        let digits = format!("{}", self.value.round() as i64);
+        let mut ans = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                ans.push(',');
+            }
+            ans.push(c);
+        }
+        ans
+    }
+
+    // inverse of `from_zh_cn_raw`
+    pub fn to_zh_cn_raw(&self) -> String {
+        format!("{}+{}", self.name.to_zh_cn(), self.to_zh_cn_value())
+    }
+}
+
+// every artifact name, some twice with characters the model tends to mix up
+const ARTIFACT_NAMES_CHS: &[&str] = &[
+    "磐陀裂生之花", "嵯峨群峰之翼", "星罗圭壁之晷", "星罗圭璧之晷", "巉岩琢塑之樽", "不动玄石之相",
+    "历经风雪的思念", "摧冰而行的执望", "冰雪故园的终期", "遍结寒霜的傲骨", "破冰踏雪的回音",
+    "染血的铁之心", "染血的黑之羽", "骑士染血之时", "染血骑士之杯", "染血的铁假面",
+    "魔女的炎之花", "魔女常燃之羽", "魔女破灭之时", "魔女的心之火", "焦灼的魔女帽",
+    "角斗士的留恋", "角斗士的归宿", "角斗士的希冀", "角斗士的酣醉", "角斗士的凯旋",
+    "饰金胸花", "追忆之风", "坚铜罗盘", "沉波之盏", "酒渍船帽",
+    "渡火者的决绝", "渡火者的解脱", "渡火者的煎熬", "渡火者的醒悟", "渡火者的智慧",
+    "远方的少女之心", "少女飘摇的思念", "少女苦短的良辰", "少女片刻的闲暇", "少女易逝的芳颜",
+    "宗室之花", "宗室之翎", "宗室时计", "宗室银瓮", "宗室面具",
+    "夏祭之花", "夏祭终末", "夏祭之刻", "夏祭水玉", "夏祭之面",
+    "平雷之心", "平雷之羽", "平雷之刻", "平雷之器", "平雷之冠",
+    "雷鸟的怜悯", "雷灾的孑遗", "雷霆的时计", "降雷的凶兆", "唤雷的头冠",
+    "野花记忆的绿野", "猎人青翠的箭羽", "翠绿猎人的笃定", "翠绿猎人的容器", "翠绿的猎人之冠",
+    "乐团的晨光", "琴师的箭羽", "终幕的时计", "终末的时计", "吟游者之壶", "指挥的礼帽",
+    "战狂的蔷薇", "战狂的翎羽", "战狂的时计", "战狂的骨杯", "战狂的鬼面",
+    "勇士的勋章", "勇士的期许", "勇士的坚毅", "勇士的壮行", "勇士的冠冕",
+    "守护之花", "守护徽印", "守护座钟", "守护之皿", "守护束带",
+    "流放者之花", "流放者之羽", "流放者怀表", "流放者之杯", "流放者头冠",
+    "赌徒的胸花", "赌徒的羽饰", "赌徒的怀表", "赌徒的骰盅", "赌徒的耳环",
+    "教官的胸花", "教官的羽饰", "教官的怀表", "教官的茶杯", "教官的帽子",
+    "武人的红花", "武人的羽饰", "武人的水漏", "武人的酒杯", "武人的头巾",
+    "祭水礼冠", "祭火礼冠", "祭雷礼冠", "祭冰礼冠",
+    "故人之心", "归乡之羽", "逐光之石", "异国之盏", "感别之冠",
+    "学士的书签", "学士的羽笔", "学士的时钟", "学士的墨杯", "学士的镜片",
+    "奇迹之花", "奇迹之羽", "奇迹之沙", "奇迹之杯", "奇迹耳坠",
+    "冒险家之花", "冒险家尾羽", "冒险家怀表", "冒险家金杯", "冒险家头带",
+    "幸运儿绿花", "幸运儿鹰羽", "幸运儿沙漏", "幸运儿之杯", "幸运儿银冠",
+    "游医的银莲", "游医的枭羽", "游医的怀钟", "游医的药壶", "游医的方巾",
+    "勋绩之花", "昭武翎羽", "金铜时晷", "盟誓金爵", "将帅兜鍪",
+    "无垢之花", "贤医之羽", "停摆之刻", "超越之盏", "嗤笑之面",
+    "明威之镡", "切落之羽", "雷云之笼", "绯花之壶", "华饰之兜",
+    "羁缠之花", "思忆之矢", "朝露之时", "祈望之心", "无常之面",
+    "荣花之期", "华馆之羽", "众生之谣", "梦醒之瓢", "形骸之笠",
+    "海染之花", "渊宫之羽", "离别之贝", "真珠之笼", "海祇之冠",
+    "生灵之华", "阳辔之遗", "潜光片羽", "结契之刻", "虺雷之姿",
+    "魂香之花", "祝祀之凭", "垂玉之叶", "涌泉之盏", "浮溯之珏",
+    "迷宫的游人", "翠蔓的智者", "贤智的定期", "迷误者之灯", "月桂的宝冠",
+    "梦中的铁花", "裁断的翎羽", "沉金的岁月", "如蜜的终宴", "沙王的投影",
+    "月女的华彩", "谢落的筵席", "凝结的时刻", "守秘的魔瓶", "紫晶的花冠",
+    "众王之都的开端", "黄金邦国的结末", "失落迷途的机芯", "迷醉长梦的守护", "流沙贵嗣的遗宝",
+    "恶龙的单片镜", "坏巫师的羽杖", "旅途中的鲜花", "水仙的时时刻刻", "勇者们的茶会",
+    "灵光明烁之心", "琦色灵彩之羽", "灵光源起之蕊", "久远花落之时", "无边酣乐之筵",
+];
+
+// the names of the `set_name` artifact in `slot`, more than one where a name is listed twice
+// above
+pub fn artifact_names_chs(set_name: &ArtifactSetName, slot: &ArtifactSlot) -> Vec<&'static str> {
+    ARTIFACT_NAMES_CHS
+        .iter()
+        .cloned()
+        .filter(|n| {
+            ArtifactSetName::from_zh_cn(n).as_ref() == Some(set_name)
+                && ArtifactSlot::from_zh_cn(n).as_ref() == Some(slot)
+        })
+        .collect()
 }
 
 pub fn get_real_artifact_name_chs(raw: &str) -> Option<String> {
-    let all_artifact_chs = [
-        "磐陀裂生之花", "嵯峨群峰之翼", "星罗圭壁之晷", "星罗圭璧之晷", "巉岩琢塑之樽", "不动玄石之相",
-        "历经风雪的思念", "摧冰而行的执望", "冰雪故园的终期", "遍结寒霜的傲骨", "破冰踏雪的回音",
-        "染血的铁之心", "染血的黑之羽", "骑士染血之时", "染血骑士之杯", "染血的铁假面",
-        "魔女的炎之花", "魔女常燃之羽", "魔女破灭之时", "魔女的心之火", "焦灼的魔女帽",
-        "角斗士的留恋", "角斗士的归宿", "角斗士的希冀", "角斗士的酣醉", "角斗士的凯旋",
-        "饰金胸花", "追忆之风", "坚铜罗盘", "沉波之盏", "酒渍船帽",
-        "渡火者的决绝", "渡火者的解脱", "渡火者的煎熬", "渡火者的醒悟", "渡火者的智慧",
-        "远方的少女之心", "少女飘摇的思念", "少女苦短的良辰", "少女片刻的闲暇", "少女易逝的芳颜",
-        "宗室之花", "宗室之翎", "宗室时计", "宗室银瓮", "宗室面具",
-        "夏祭之花", "夏祭终末", "夏祭之刻", "夏祭水玉", "夏祭之面",
-        "平雷之心", "平雷之羽", "平雷之刻", "平雷之器", "平雷之冠",
-        "雷鸟的怜悯", "雷灾的孑遗", "雷霆的时计", "降雷的凶兆", "唤雷的头冠",
-        "野花记忆的绿野", "猎人青翠的箭羽", "翠绿猎人的笃定", "翠绿猎人的容器", "翠绿的猎人之冠",
-        "乐团的晨光", "琴师的箭羽", "终幕的时计", "终末的时计", "吟游者之壶", "指挥的礼帽",
-        "战狂的蔷薇", "战狂的翎羽", "战狂的时计", "战狂的骨杯", "战狂的鬼面",
-        "勇士的勋章", "勇士的期许", "勇士的坚毅", "勇士的壮行", "勇士的冠冕",
-        "守护之花", "守护徽印", "守护座钟", "守护之皿", "守护束带",
-        "流放者之花", "流放者之羽", "流放者怀表", "流放者之杯", "流放者头冠",
-        "赌徒的胸花", "赌徒的羽饰", "赌徒的怀表", "赌徒的骰盅", "赌徒的耳环",
-        "教官的胸花", "教官的羽饰", "教官的怀表", "教官的茶杯", "教官的帽子",
-        "武人的红花", "武人的羽饰", "武人的水漏", "武人的酒杯", "武人的头巾",
-        "祭水礼冠", "祭火礼冠", "祭雷礼冠", "祭冰礼冠",
-        "故人之心", "归乡之羽", "逐光之石", "异国之盏", "感别之冠",
-        "学士的书签", "学士的羽笔", "学士的时钟", "学士的墨杯", "学士的镜片",
-        "奇迹之花", "奇迹之羽", "奇迹之沙", "奇迹之杯", "奇迹耳坠",
-        "冒险家之花", "冒险家尾羽", "冒险家怀表", "冒险家金杯", "冒险家头带",
-        "幸运儿绿花", "幸运儿鹰羽", "幸运儿沙漏", "幸运儿之杯", "幸运儿银冠",
-        "游医的银莲", "游医的枭羽", "游医的怀钟", "游医的药壶", "游医的方巾",
-        "勋绩之花", "昭武翎羽", "金铜时晷", "盟誓金爵", "将帅兜鍪",
-        "无垢之花", "贤医之羽", "停摆之刻", "超越之盏", "嗤笑之面",
-        "明威之镡", "切落之羽", "雷云之笼", "绯花之壶", "华饰之兜",
-        "羁缠之花", "思忆之矢", "朝露之时", "祈望之心", "无常之面",
-        "荣花之期", "华馆之羽", "众生之谣", "梦醒之瓢", "形骸之笠",
-        "海染之花", "渊宫之羽", "离别之贝", "真珠之笼", "海祇之冠",
-        "生灵之华", "阳辔之遗", "潜光片羽", "结契之刻", "虺雷之姿",
-        "魂香之花", "祝祀之凭", "垂玉之叶", "涌泉之盏", "浮溯之珏",
-        "迷宫的游人", "翠蔓的智者", "贤智的定期", "迷误者之灯", "月桂的宝冠",
-        "梦中的铁花", "裁断的翎羽", "沉金的岁月", "如蜜的终宴", "沙王的投影",
-        "月女的华彩", "谢落的筵席", "凝结的时刻", "守秘的魔瓶", "紫晶的花冠",
-        "众王之都的开端", "黄金邦国的结末", "失落迷途的机芯", "迷醉长梦的守护", "流沙贵嗣的遗宝",
-        "恶龙的单片镜", "坏巫师的羽杖", "旅途中的鲜花", "水仙的时时刻刻", "勇者们的茶会",
-        "灵光明烁之心", "琦色灵彩之羽", "灵光源起之蕊", "久远花落之时", "无边酣乐之筵",
-    ];
+    let all_artifact_chs = ARTIFACT_NAMES_CHS;
 
     let mut min_index = 0;
     let mut min_dis = edit_distance::edit_distance(raw, all_artifact_chs[0]);
@@ -669,3 +850,83 @@ impl ArtifactSlot {
     }
 }
 
+
+// artifacts for the unit tests of this and other modules
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::*;
+
+    // a 5 star Gladiator's Finale flower with the given level and sub stats
+    pub fn artifact(level: u32, subs: &[(ArtifactStatName, f64)]) -> InternalArtifact {
+        let stat = |i: usize| {
+            subs.get(i).map(|(name, value)| ArtifactStat {
+                name: name.clone(),
+                value: *value,
+            })
+        };
+        InternalArtifact {
+            set_name: ArtifactSetName::GladiatorFinale,
+            slot: ArtifactSlot::Flower,
+            star: 5,
+            level,
+            main_stat: ArtifactStat {
+                name: ArtifactStatName::Hp,
+                value: 4780.0,
+            },
+            sub_stat_1: stat(0),
+            sub_stat_2: stat(1),
+            sub_stat_3: stat(2),
+            sub_stat_4: stat(3),
+            equip: None,
+            id: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_util::artifact;
+    use super::ArtifactStatName::*;
+    use super::*;
+
+    fn ids(artifacts: &mut [InternalArtifact]) -> Vec<String> {
+        assign_ids(artifacts);
+        artifacts.iter().map(|a| a.id.clone().unwrap()).collect()
+    }
+
+    #[test]
+    fn ids_are_deterministic() {
+        let mut a = vec![artifact(0, &[(Critical, 0.039), (Atk, 19.0), (Def, 23.0)])];
+        let mut b = a.clone();
+        assert_eq!(ids(&mut a), ids(&mut b));
+    }
+
+    #[test]
+    fn ids_survive_upgrades() {
+        let mut before = vec![artifact(0, &[(Critical, 0.039), (Atk, 19.0), (Def, 23.0)])];
+        let mut after = vec![artifact(
+            8,
+            &[(Critical, 0.078), (Atk, 19.0), (Def, 42.0), (Hp, 209.0)],
+        )];
+        after[0].equip = Some(String::from("胡桃"));
+        assert_eq!(ids(&mut before), ids(&mut after));
+    }
+
+    #[test]
+    fn look_alikes_get_different_ids() {
+        let a = artifact(0, &[(Critical, 0.039), (Atk, 19.0), (Def, 23.0)]);
+        let mut list = vec![a.clone(), a];
+        let ids = ids(&mut list);
+        assert_ne!(ids[0], ids[1]);
+    }
+
+    #[test]
+    fn existing_ids_are_kept() {
+        let mut a = artifact(0, &[(Critical, 0.039)]);
+        a.id = Some(String::from("mine"));
+        let mut list = vec![a, artifact(0, &[(Critical, 0.039)])];
+        let ids = ids(&mut list);
+        assert_eq!(ids[0], "mine");
+        assert_ne!(ids[1], "mine");
+    }
+}
@@ -0,0 +1,319 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use serde::Serialize;
+
+use crate::artifact::internal_artifact::{ArtifactStat, ArtifactStatName, InternalArtifact};
+
+// share of the highest roll an upgrade gives on average
+const AVG_ROLL: f64 = 0.85;
+
+pub const DEFAULT_PRESET: &str = "crit";
+pub const DEFAULT_FODDER_BELOW: f64 = 3.0;
+
+// The highest value a single sub stat roll can give, in displayed units (percent numbers for
+// percentages). Stats that are never sub stats have none.
+fn max_roll(name: &ArtifactStatName, star: u32) -> Option<f64> {
+    let five = match name {
+        ArtifactStatName::Hp => 298.75,
+        ArtifactStatName::Atk => 19.45,
+        ArtifactStatName::Def => 23.15,
+        ArtifactStatName::HpPercentage => 5.83,
+        ArtifactStatName::AtkPercentage => 5.83,
+        ArtifactStatName::DefPercentage => 7.29,
+        ArtifactStatName::ElementalMastery => 23.31,
+        ArtifactStatName::Recharge => 6.48,
+        ArtifactStatName::Critical => 3.89,
+        ArtifactStatName::CriticalDamage => 7.77,
+        _ => return None,
+    };
+    // 4 star rolls are 80% of 5 star ones
+    match star {
+        5 => Some(five),
+        4 => Some(five * 0.8),
+        _ => None,
+    }
+}
+
+fn max_level(star: u32) -> u32 {
+    match star {
+        1 | 2 => 4,
+        3 => 12,
+        4 => 16,
+        _ => 20,
+    }
+}
+
+// sub stats are upgraded at every 4th level, a +3 still has all of them ahead
+fn upgrades_left(star: u32, level: u32) -> u32 {
+    (max_level(star) / 4).saturating_sub(level / 4)
+}
+
+// Worth of one max roll of each sub stat, from 0 to 1. Stats not listed count 0.
+pub struct StatWeights {
+    weights: HashMap<ArtifactStatName, f64>,
+}
+
+// name, then (GOOD stat key, weight)
+const PRESETS: [(&str, &[(&str, f64)]); 6] = [
+    ("crit", &[("critRate_", 1.0), ("critDMG_", 1.0)]),
+    ("atk", &[("critRate_", 1.0), ("critDMG_", 1.0), ("atk_", 0.75), ("atk", 0.25)]),
+    ("hp", &[("critRate_", 1.0), ("critDMG_", 1.0), ("hp_", 0.75), ("hp", 0.25)]),
+    ("def", &[("critRate_", 1.0), ("critDMG_", 1.0), ("def_", 0.75), ("def", 0.25)]),
+    ("em", &[("critRate_", 1.0), ("critDMG_", 1.0), ("eleMas", 0.75), ("atk_", 0.5)]),
+    ("support", &[("enerRech_", 1.0), ("hp_", 0.5), ("atk_", 0.5), ("critRate_", 0.5)]),
+];
+
+pub fn preset_names() -> Vec<&'static str> {
+    PRESETS.iter().map(|(name, _)| *name).collect()
+}
+
+impl StatWeights {
+    fn from_good_keys(weights: &[(&str, f64)]) -> Result<StatWeights, String> {
+        let mut ans = HashMap::new();
+        for (key, weight) in weights.iter() {
+            if !(0.0..=1.0).contains(weight) {
+                return Err(format!("weight of {} must be between 0 and 1, got {}", key, weight));
+            }
+            match ArtifactStatName::from_good(key) {
+                Some(name) => ans.insert(name, *weight),
+                None => return Err(format!("unknown stat key {}", key)),
+            };
+        }
+        Ok(StatWeights { weights: ans })
+    }
+
+    pub fn preset(name: &str) -> Option<StatWeights> {
+        PRESETS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, w)| StatWeights::from_good_keys(w).unwrap())
+    }
+
+    // The preset `name`, looked up in `path` first when given. The file is a JSON object of
+    // presets, e.g. per character, keyed by GOOD stat keys:
+    //
+    //   { "胡桃": { "critRate_": 1.0, "critDMG_": 1.0, "hp_": 0.8, "eleMas": 0.5 } }
+    pub fn load(path: Option<&str>, name: &str) -> Result<StatWeights, String> {
+        if let Some(path) = path {
+            let content = match fs::read_to_string(path) {
+                Ok(v) => v,
+                Err(e) => return Err(format!("cannot read {}: {}", path, e)),
+            };
+            let presets: BTreeMap<String, BTreeMap<String, f64>> =
+                match serde_json::from_str(&content) {
+                    Ok(v) => v,
+                    Err(e) => return Err(format!("cannot parse {}: {}", path, e)),
+                };
+            if let Some(weights) = presets.get(name) {
+                let weights: Vec<(&str, f64)> =
+                    weights.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+                return StatWeights::from_good_keys(&weights);
+            }
+        }
+
+        match StatWeights::preset(name) {
+            Some(v) => Ok(v),
+            None => Err(format!("unknown preset {}", name)),
+        }
+    }
+
+    pub fn get(&self, name: &ArtifactStatName) -> f64 {
+        *self.weights.get(name).unwrap_or(&0.0)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactScore {
+    // 2 * crit rate + crit damage, in percent
+    pub crit_value: f64,
+    // sub stat values counted in max rolls
+    pub rolls: f64,
+    // rolls times their weight
+    pub score: f64,
+    // share of the rolls that went into weighted stats, 0 to 1
+    pub efficiency: f64,
+    // expected score at max level, when the remaining upgrades hit the sub stats evenly
+    pub potential: f64,
+    pub fodder: bool,
+}
+
+fn rolls(stat: &ArtifactStat, star: u32) -> f64 {
+    match max_roll(&stat.name, star) {
+        Some(m) => stat.display_value() / m,
+        None => 0.0,
+    }
+}
+
+pub fn score(artifact: &InternalArtifact, weights: &StatWeights, fodder_below: f64) -> ArtifactScore {
+    let mut crit_value = 0.0;
+    let mut total_rolls = 0.0;
+    let mut total_score = 0.0;
+    let mut weight_sum = 0.0;
+    let mut count = 0;
+    for stat in artifact.sub_stats() {
+        match stat.name {
+            ArtifactStatName::Critical => crit_value += stat.display_value() * 2.0,
+            ArtifactStatName::CriticalDamage => crit_value += stat.display_value(),
+            _ => (),
+        }
+        let r = rolls(stat, artifact.star);
+        let w = weights.get(&stat.name);
+        total_rolls += r;
+        total_score += r * w;
+        weight_sum += w;
+        count += 1;
+    }
+
+    let max_weight = weights.weights.values().cloned().fold(0.0, f64::max);
+    let efficiency = if total_rolls > 0.0 && max_weight > 0.0 {
+        total_score / (total_rolls * max_weight)
+    } else {
+        0.0
+    };
+    let upgrades_left = upgrades_left(artifact.star, artifact.level);
+    let potential = if count > 0 {
+        total_score + upgrades_left as f64 * AVG_ROLL * weight_sum / count as f64
+    } else {
+        total_score
+    };
+
+    ArtifactScore {
+        crit_value,
+        rolls: total_rolls,
+        score: total_score,
+        efficiency,
+        potential,
+        fodder: potential < fodder_below,
+    }
+}
+
+// highest score first, ties by potential
+pub fn rank<'a>(
+    artifacts: &'a [InternalArtifact],
+    weights: &StatWeights,
+    fodder_below: f64,
+) -> Vec<(&'a InternalArtifact, ArtifactScore)> {
+    let mut ans: Vec<(&InternalArtifact, ArtifactScore)> = artifacts
+        .iter()
+        .map(|a| (a, score(a, weights, fodder_below)))
+        .collect();
+    ans.sort_by(|(_, a), (_, b)| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then(b.potential.partial_cmp(&a.potential).unwrap_or(Ordering::Equal))
+    });
+    ans
+}
+
+// one line of the JSON report
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreEntry<'a> {
+    pub rank: usize,
+    pub id: Option<&'a str>,
+    pub set_key: &'static str,
+    pub slot_key: &'static str,
+    pub rarity: u32,
+    pub level: u32,
+    pub main_stat_key: &'static str,
+    #[serde(flatten)]
+    pub score: &'a ArtifactScore,
+}
+
+pub fn report<'a>(ranked: &'a [(&'a InternalArtifact, ArtifactScore)]) -> Vec<ScoreEntry<'a>> {
+    ranked
+        .iter()
+        .enumerate()
+        .map(|(i, (a, s))| ScoreEntry {
+            rank: i + 1,
+            id: a.id.as_deref(),
+            set_key: a.set_name.to_good(),
+            slot_key: a.slot.to_good(),
+            rarity: a.star,
+            level: a.level,
+            main_stat_key: a.main_stat.name.to_good(),
+            score: s,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::internal_artifact::test_util::artifact;
+    use crate::artifact::internal_artifact::ArtifactStatName::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn upgrades_left_counts_every_fourth_level() {
+        assert_eq!(upgrades_left(5, 0), 5);
+        assert_eq!(upgrades_left(5, 3), 5);
+        assert_eq!(upgrades_left(5, 4), 4);
+        assert_eq!(upgrades_left(5, 19), 1);
+        assert_eq!(upgrades_left(5, 20), 0);
+        assert_eq!(upgrades_left(4, 12), 1);
+        assert_eq!(upgrades_left(4, 16), 0);
+        assert_eq!(upgrades_left(3, 8), 1);
+        assert_eq!(upgrades_left(2, 0), 1);
+        assert_eq!(upgrades_left(1, 4), 0);
+    }
+
+    #[test]
+    fn crit_value_and_rolls() {
+        let weights = StatWeights::preset("crit").unwrap();
+        let a = artifact(20, &[(Critical, 0.0389), (CriticalDamage, 0.0777), (Atk, 19.45)]);
+        let s = score(&a, &weights, DEFAULT_FODDER_BELOW);
+        assert!(close(s.crit_value, 2.0 * 3.89 + 7.77));
+        assert!(close(s.rolls, 3.0));
+        assert!(close(s.score, 2.0));
+        assert!(close(s.efficiency, 2.0 / 3.0));
+        assert!(close(s.potential, s.score));
+    }
+
+    #[test]
+    fn potential_counts_remaining_upgrades() {
+        let weights = StatWeights::preset("crit").unwrap();
+        let a = artifact(3, &[(Critical, 0.0389), (Atk, 19.45)]);
+        let s = score(&a, &weights, DEFAULT_FODDER_BELOW);
+        assert!(close(s.score, 1.0));
+        // 5 upgrades, half of them on average into crit rate
+        assert!(close(s.potential, 1.0 + 5.0 * AVG_ROLL * 0.5));
+        assert!(!s.fodder);
+
+        let junk = artifact(3, &[(Atk, 19.45), (Def, 23.15)]);
+        assert!(score(&junk, &weights, DEFAULT_FODDER_BELOW).fodder);
+    }
+
+    #[test]
+    fn rank_sorts_by_score_then_potential() {
+        let weights = StatWeights::preset("crit").unwrap();
+        let artifacts = vec![
+            artifact(20, &[(Atk, 19.45)]),
+            artifact(20, &[(Critical, 0.0389)]),
+            artifact(0, &[(Critical, 0.0389)]),
+        ];
+        let ranked = rank(&artifacts, &weights, DEFAULT_FODDER_BELOW);
+        let levels: Vec<u32> = ranked.iter().map(|(a, _)| a.level).collect();
+        assert_eq!(levels, vec![0, 20, 20]);
+        assert!(close(ranked[2].1.score, 0.0));
+    }
+
+    #[test]
+    fn invalid_weights_are_rejected() {
+        assert!(StatWeights::preset("nope").is_none());
+        assert!(StatWeights::from_good_keys(&[("foo_", 1.0)]).is_err());
+        assert!(StatWeights::from_good_keys(&[("atk_", -0.5)]).is_err());
+        assert!(StatWeights::from_good_keys(&[("atk_", 1.5)]).is_err());
+        assert!(StatWeights::from_good_keys(&[("atk_", f64::NAN)]).is_err());
+        assert!(StatWeights::from_good_keys(&[("atk_", f64::INFINITY)]).is_err());
+        assert!(StatWeights::from_good_keys(&[("atk_", 0.0), ("hp_", 1.0)]).is_ok());
+        assert!(StatWeights::load(None, "nope").is_err());
+    }
+}
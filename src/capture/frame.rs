@@ -0,0 +1,113 @@
+use crate::capture::{capture_area_on, find_screen};
+use crate::common::color::Color;
+use crate::common::{PixelRect, RawCaptureImage};
+
+// One capture of the game window or a part of it, in BGRA. Regions are given in virtual desktop
+// coordinates, the same as for `capture_absolute`; pixels outside the frame read as black.
+pub struct Frame {
+    data: Vec<u8>,
+    rect: PixelRect,
+}
+
+impl Frame {
+    fn pixel(&self, x: i32, y: i32) -> [u8; 4] {
+        let fx = x - self.rect.left;
+        let fy = y - self.rect.top;
+        if fx < 0 || fy < 0 || fx >= self.rect.width || fy >= self.rect.height {
+            return [0, 0, 0, 255];
+        }
+        let offset = (fy * self.rect.width + fx) as usize * 4;
+        [
+            self.data[offset],
+            self.data[offset + 1],
+            self.data[offset + 2],
+            self.data[offset + 3],
+        ]
+    }
+
+    // BGRA pixels of `rect`, like `capture_absolute` would return them
+    pub fn crop(&self, rect: &PixelRect) -> Vec<u8> {
+        let mut ans = Vec::with_capacity((rect.width * rect.height * 4) as usize);
+        for y in rect.top..rect.top + rect.height {
+            for x in rect.left..rect.left + rect.width {
+                ans.extend_from_slice(&self.pixel(x, y));
+            }
+        }
+        ans
+    }
+
+    pub fn crop_capture(&self, rect: &PixelRect) -> RawCaptureImage {
+        RawCaptureImage {
+            data: self.crop(rect),
+            w: rect.width as u32,
+            h: rect.height as u32,
+        }
+    }
+
+    pub fn get_color(&self, x: i32, y: i32) -> Color {
+        let p = self.pixel(x, y);
+        Color::from(p[2], p[1], p[0])
+    }
+}
+
+// Grabs frames of one window. The screen the window is on is looked up once.
+pub struct FrameGrabber {
+    screen: screenshots::Screen,
+    rect: PixelRect,
+}
+
+impl FrameGrabber {
+    pub fn new(rect: PixelRect) -> Result<FrameGrabber, String> {
+        Ok(FrameGrabber {
+            screen: find_screen(&rect)?,
+            rect,
+        })
+    }
+
+    fn bgra(&self, rect: &PixelRect) -> Result<Vec<u8>, String> {
+        let (mut buffer, is_bgra) = capture_area_on(&self.screen, rect)?;
+        if !is_bgra {
+            for chunk in buffer.chunks_mut(4) {
+                chunk.swap(0, 2);
+            }
+        }
+        Ok(buffer)
+    }
+
+    pub fn grab(&self) -> Result<Frame, String> {
+        self.grab_rect(&self.rect)
+    }
+
+    // a frame of only part of the window
+    pub fn grab_rect(&self, rect: &PixelRect) -> Result<Frame, String> {
+        Ok(Frame {
+            data: self.bgra(rect)?,
+            rect: rect.clone(),
+        })
+    }
+
+    // a single region, for checks that need no more than that (e.g while scrolling)
+    pub fn capture(&self, rect: &PixelRect) -> Result<Vec<u8>, String> {
+        self.bgra(rect)
+    }
+
+    // mean color of the (2 * radius + 1)^2 pixels around (x, y), steadier than a single pixel
+    // on compressed video
+    pub fn get_color(&self, x: i32, y: i32, radius: i32) -> Result<Color, String> {
+        let size = radius * 2 + 1;
+        let data = self.bgra(&PixelRect {
+            left: x - radius,
+            top: y - radius,
+            width: size,
+            height: size,
+        })?;
+        let n = (size * size) as u32;
+        let mut sum = [0_u32; 3];
+        for p in data.chunks(4) {
+            sum[0] += p[0] as u32;
+            sum[1] += p[1] as u32;
+            sum[2] += p[2] as u32;
+        }
+        Ok(Color::from((sum[2] / n) as u8, (sum[1] / n) as u8, (sum[0] / n) as u8))
+    }
+}
@@ -3,20 +3,81 @@ use image::{Rgb, RgbImage};
 use crate::common::color::Color;
 use crate::common::PixelRect;
 
+pub mod frame;
+
+// The screen holding the center of `rect`. Screen origins are in virtual desktop pixels like
+// the window rect, sizes are logical and scaled by the screen's scale factor.
+pub(crate) fn find_screen(rect: &PixelRect) -> Result<screenshots::Screen, String> {
+    let screens = screenshots::Screen::all().ok_or("cannot get DisplayInfo")?;
+    let cx = rect.left + rect.width / 2;
+    let cy = rect.top + rect.height / 2;
+    let contains = |screen: &screenshots::Screen| {
+        let d = &screen.display_info;
+        let w = (d.width as f32 * d.scale_factor) as i32;
+        let h = (d.height as f32 * d.scale_factor) as i32;
+        cx >= d.x && cx < d.x + w && cy >= d.y && cy < d.y + h
+    };
+
+    match screens.iter().find(|s| contains(s)) {
+        Some(screen) => Ok(*screen),
+        None => screens.first().copied().ok_or(String::from("no screen found")),
+    }
+}
+
+// nearest neighbour resize of a 4 bytes per pixel buffer to `width` x `height`
+fn resize_buffer(buffer: Vec<u8>, width: u32, height: u32) -> Vec<u8> {
+    let pixels = buffer.len() / 4;
+    if pixels == (width * height) as usize || pixels == 0 {
+        return buffer;
+    }
+    // the screen scaled both sides by the same factor
+    let scale = (pixels as f64 / (width * height) as f64).sqrt();
+    let bw = ((width as f64 * scale).round() as usize).max(1);
+    let bh = pixels / bw;
+
+    let mut ans = vec![0_u8; (width * height * 4) as usize];
+    for y in 0..height as usize {
+        let sy = (y * bh / height as usize).min(bh - 1);
+        for x in 0..width as usize {
+            let sx = (x * bw / width as usize).min(bw - 1);
+            let src = (sy * bw + sx) * 4;
+            let dst = (y * width as usize + x) * 4;
+            ans[dst..dst + 4].copy_from_slice(&buffer[src..src + 4]);
+        }
+    }
+    ans
+}
+
+// Captures `rect` (virtual desktop coordinates, physical pixels) from the screen it is on.
+// Returns the buffer, always width x height pixels, and whether it is bgra.
+fn capture_area(rect: &PixelRect) -> Result<(Vec<u8>, bool), String> {
+    let screen = find_screen(rect)?;
+    capture_area_on(&screen, rect)
+}
+
+pub(crate) fn capture_area_on(
+    screen: &screenshots::Screen,
+    rect: &PixelRect,
+) -> Result<(Vec<u8>, bool), String> {
+    let d = &screen.display_info;
+    let scale = d.scale_factor.max(0.1);
+    // capture_area takes logical coordinates relative to the screen
+    let x = ((rect.left - d.x) as f32 / scale).round() as i32;
+    let y = ((rect.top - d.y) as f32 / scale).round() as i32;
+    let w = ((rect.width as f32 / scale).round() as u32).max(1);
+    let h = ((rect.height as f32 / scale).round() as u32).max(1);
+    let (buffer, is_bgra) = screen.capture_area(x, y, w, h).ok_or("capture failed")?;
+
+    Ok((
+        resize_buffer(buffer, rect.width as u32, rect.height as u32),
+        is_bgra,
+    ))
+}
+
 /// retures Ok(buf) on success
 /// buf contains pixels in [b:u8, g:u8, r:u8, a:u8] format, as an `[[i32;width];height]`.
-pub fn capture_absolute(
-    PixelRect {
-        left,
-        top,
-        width,
-        height,
-    }: &PixelRect,
-) -> Result<Vec<u8>, String> {
-    let screen = screenshots::Screen::all().ok_or("cannot get DisplayInfo")?[0];
-    let (mut buffer, is_bgra) = screen
-        .capture_area(*left, *top, *width as u32, *height as u32)
-        .ok_or("capture failed")?;
+pub fn capture_absolute(rect: &PixelRect) -> Result<Vec<u8>, String> {
+    let (mut buffer, is_bgra) = capture_area(rect)?;
 
     if !is_bgra {
         for chunk in buffer.chunks_mut(4) {
@@ -29,22 +90,11 @@ pub fn capture_absolute(
     Ok(buffer)
 }
 
-pub fn capture_absolute_image(
-    PixelRect {
-        left,
-        top,
-        width,
-        height,
-    }: &PixelRect,
-) -> Result<image::RgbImage, String> {
-    // simply use the first screen.
-    // todo: multi-screen support
-    let screen = screenshots::Screen::all().ok_or("cannot get DisplayInfo")?[0];
-    let (buffer, is_bgra) = screen
-        .capture_area(*left, *top, *width as u32, *height as u32)
-        .ok_or("capture failed")?;
-    Ok(RgbImage::from_fn(*width as u32, *height as u32, |x, y| {
-        let offset = (y * (*width as u32) + x) as usize;
+pub fn capture_absolute_image(rect: &PixelRect) -> Result<image::RgbImage, String> {
+    let (buffer, is_bgra) = capture_area(rect)?;
+    let width = rect.width as u32;
+    Ok(RgbImage::from_fn(width, rect.height as u32, |x, y| {
+        let offset = (y * width + x) as usize * 4;
         if is_bgra {
             Rgb([buffer[offset + 2], buffer[offset + 1], buffer[offset]])
         } else {
@@ -1,11 +1,18 @@
+pub const SAME_COLOR_TOLERANCE: u32 = 20;
+// video compression of streamed clients shifts colors by up to about 10 per channel
+pub const STREAM_COLOR_TOLERANCE: u32 = 300;
+
 #[derive(Debug)]
 pub struct Color (pub u8, pub u8, pub u8);
 
 impl Color {
     pub fn is_same(&self, other: &Color) -> bool {
-        let dis = self.dis_2(other);
+        self.is_same_within(other, SAME_COLOR_TOLERANCE)
+    }
 
-        dis < 20
+    // `tolerance` is the squared distance below which colors count as the same
+    pub fn is_same_within(&self, other: &Color, tolerance: u32) -> bool {
+        self.dis_2(other) < tolerance
     }
 
     pub fn dis_2(&self, other: &Color) -> u32 {
@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::Deserialize;
+
+// Settings read from yas.toml. Every key is optional and mirrors the command line option of
+// the same name, e.g.
+//
+//   scroll-stop = 120
+//   max-wait-switch-artifact = 1000
+//   output-format = "good,mona"
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ConfigFile {
+    pub max_row: Option<u32>,
+    pub min_star: Option<u32>,
+    pub min_level: Option<u32>,
+    pub max_wait_switch_artifact: Option<u32>,
+    pub scroll_stop: Option<u32>,
+    pub number: Option<u32>,
+    pub cloud_wait_switch_artifact: Option<u32>,
+    pub verbose: Option<bool>,
+    pub offset_x: Option<i32>,
+    pub offset_y: Option<i32>,
+    pub auto_offset: Option<bool>,
+    pub layout: Option<String>,
+    pub layout_file: Option<String>,
+    pub aspect_tolerance: Option<f64>,
+    pub adaptive_timing: Option<bool>,
+    pub timing_profile: Option<String>,
+    pub paging: Option<String>,
+    pub cloud: Option<bool>,
+    pub dataset: Option<String>,
+    pub output_dir: Option<String>,
+    pub output_format: Option<String>,
+    pub output_name: Option<String>,
+}
+
+pub const CONFIG_FILE_NAME: &str = "yas.toml";
+
+// <config dir>/yas/yas.toml, e.g. ~/.config/yas/yas.toml or %APPDATA%\yas\yas.toml
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|p| p.join("yas").join(CONFIG_FILE_NAME))
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<ConfigFile, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+        };
+        match toml::from_str(&content) {
+            Ok(v) => {
+                info!("loaded config from {}", path.display());
+                Ok(v)
+            }
+            Err(e) => Err(format!("cannot parse {}: {}", path.display(), e)),
+        }
+    }
+
+    // The per-user file, then ./yas.toml on top of it. Missing files are skipped.
+    pub fn load_default() -> Result<ConfigFile, String> {
+        let mut config = ConfigFile::default();
+        let paths = user_config_path()
+            .into_iter()
+            .chain(std::iter::once(PathBuf::from(CONFIG_FILE_NAME)));
+        for path in paths {
+            if path.exists() {
+                config = config.merge(ConfigFile::load(&path)?);
+            }
+        }
+
+        Ok(config)
+    }
+
+    // values present in `other` win
+    pub fn merge(self, other: ConfigFile) -> ConfigFile {
+        ConfigFile {
+            max_row: other.max_row.or(self.max_row),
+            min_star: other.min_star.or(self.min_star),
+            min_level: other.min_level.or(self.min_level),
+            max_wait_switch_artifact: other.max_wait_switch_artifact.or(self.max_wait_switch_artifact),
+            scroll_stop: other.scroll_stop.or(self.scroll_stop),
+            number: other.number.or(self.number),
+            cloud_wait_switch_artifact: other
+                .cloud_wait_switch_artifact
+                .or(self.cloud_wait_switch_artifact),
+            verbose: other.verbose.or(self.verbose),
+            offset_x: other.offset_x.or(self.offset_x),
+            offset_y: other.offset_y.or(self.offset_y),
+            auto_offset: other.auto_offset.or(self.auto_offset),
+            layout: other.layout.or(self.layout),
+            layout_file: other.layout_file.or(self.layout_file),
+            aspect_tolerance: other.aspect_tolerance.or(self.aspect_tolerance),
+            adaptive_timing: other.adaptive_timing.or(self.adaptive_timing),
+            timing_profile: other.timing_profile.or(self.timing_profile),
+            paging: other.paging.or(self.paging),
+            cloud: other.cloud.or(self.cloud),
+            dataset: other.dataset.or(self.dataset),
+            output_dir: other.output_dir.or(self.output_dir),
+            output_format: other.output_format.or(self.output_format),
+            output_name: other.output_name.or(self.output_name),
+        }
+    }
+}
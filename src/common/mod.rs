@@ -10,8 +10,10 @@ pub mod utils;
 pub mod buffer;
 pub mod color;
 pub mod character_name;
+pub mod window;
+pub mod config_file;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PixelRect {
     pub left: i32,
     pub top: i32,
@@ -66,7 +68,7 @@ impl PixelRectBound {
             height: h,
         };
         let now = SystemTime::now();
-        let raw_u8 = capture::capture_absolute(&rect).unwrap();
+        let raw_u8 = capture::capture_absolute(&rect)?;
         info!("capture raw time: {}ms", now.elapsed().unwrap().as_millis());
         let raw_gray = to_gray(raw_u8, w as u32, h as u32);
         let raw_after_pp = pre_process(raw_gray);
@@ -1,11 +1,8 @@
 use std::fs;
-use std::io::stdin;
-use std::process;
 use std::time::Duration;
 use std::{thread, time};
 
 use crate::dto::GithubTag;
-use log::error;
 use reqwest::blocking::Client;
 use reqwest::header::{HeaderValue, USER_AGENT};
 
@@ -14,6 +11,11 @@ mod windows;
 #[cfg(windows)]
 pub use windows::*;
 
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::*;
+
 pub fn sleep(ms: u32) {
     let time = time::Duration::from_millis(ms as u64);
     thread::sleep(time);
@@ -24,11 +26,42 @@ pub fn read_file_to_string(path: String) -> String {
     content
 }
 
-pub fn error_and_quit(msg: &str) -> ! {
-    error!("{}, 按Enter退出", msg);
-    let mut s: String = String::new();
-    stdin().read_line(&mut s);
-    process::exit(0);
+// cloud genshin, in the native client or a browser tab
+pub fn is_cloud_title(title: &str) -> bool {
+    title.contains("云·原神") || title.contains("云原神")
+}
+
+// columns `c` takes in a terminal, CJK characters and full width forms take two
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115f
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+pub fn display_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+// `s` padded with spaces to `width` columns, on the left for `right_align`
+pub fn pad(s: &str, width: usize, right_align: bool) -> String {
+    let spaces = " ".repeat(width.saturating_sub(display_width(s)));
+    if right_align {
+        spaces + s
+    } else {
+        String::from(s) + &spaces
+    }
 }
 
 #[cfg(not(windows))]
@@ -66,3 +99,24 @@ pub fn check_update() -> Option<String> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("双暴"), 4);
+        assert_eq!(display_width("暴击率10.5%"), 11);
+        assert_eq!(display_width("（是）"), 6);
+    }
+
+    #[test]
+    fn pad_uses_display_width() {
+        assert_eq!(pad("双暴", 6, true), "  双暴");
+        assert_eq!(pad("是", 4, false), "是  ");
+        assert_eq!(pad("12.5", 6, true), "  12.5");
+        assert_eq!(pad("有效率", 4, true), "有效率");
+    }
+}
@@ -0,0 +1,219 @@
+use log::info;
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ClientMessageEvent, ConfigureWindowAux, ConnectionExt, EventMask,
+    InputFocus, StackMode,
+};
+use x11rb::rust_connection::RustConnection;
+use x11rb::CURRENT_TIME;
+
+use crate::common::PixelRect;
+
+pub use x11rb::protocol::xproto::Window;
+
+fn connect() -> Result<(RustConnection, Window), String> {
+    let (conn, screen_num) = match x11rb::connect(None) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("cannot connect to X server: {}", e)),
+    };
+    let root = conn.setup().roots[screen_num].root;
+
+    Ok((conn, root))
+}
+
+fn intern_atom(conn: &RustConnection, name: &str) -> Result<Atom, String> {
+    conn.intern_atom(false, name.as_bytes())
+        .map_err(ReplyError::from)
+        .and_then(|c| c.reply())
+        .map(|r| r.atom)
+        .map_err(|e| format!("cannot intern atom {}: {}", name, e))
+}
+
+fn get_property(conn: &RustConnection, window: Window, property: Atom, type_: Atom) -> Option<Vec<u8>> {
+    let reply = conn
+        .get_property(false, window, property, type_, 0, u32::MAX)
+        .ok()?
+        .reply()
+        .ok()?;
+    if reply.value.is_empty() {
+        None
+    } else {
+        Some(reply.value)
+    }
+}
+
+fn get_window_title(conn: &RustConnection, window: Window) -> String {
+    let utf8 = intern_atom(conn, "UTF8_STRING");
+    let net_wm_name = intern_atom(conn, "_NET_WM_NAME");
+    if let (Ok(utf8), Ok(net_wm_name)) = (utf8, net_wm_name) {
+        if let Some(v) = get_property(conn, window, net_wm_name, utf8) {
+            return String::from_utf8_lossy(&v).into_owned();
+        }
+    }
+
+    match get_property(conn, window, AtomEnum::WM_NAME.into(), AtomEnum::ANY.into()) {
+        Some(v) => String::from_utf8_lossy(&v).into_owned(),
+        None => String::new(),
+    }
+}
+
+// WM_CLASS holds the instance and class name, separated by NUL
+fn parse_window_class(value: &[u8]) -> Vec<String> {
+    value
+        .split(|&c| c == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
+fn get_window_class(conn: &RustConnection, window: Window) -> Vec<String> {
+    match get_property(conn, window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into()) {
+        Some(v) => parse_window_class(&v),
+        None => Vec::new(),
+    }
+}
+
+fn collect_children(conn: &RustConnection, window: Window, ans: &mut Vec<Window>) {
+    let tree = match conn.query_tree(window).ok().and_then(|c| c.reply().ok()) {
+        Some(v) => v,
+        None => return,
+    };
+    for child in tree.children {
+        ans.push(child);
+        collect_children(conn, child, ans);
+    }
+}
+
+// top-level windows as reported by the window manager, or every window if there is none
+fn list_windows(conn: &RustConnection, root: Window) -> Vec<Window> {
+    if let Ok(client_list) = intern_atom(conn, "_NET_CLIENT_LIST") {
+        let reply = conn
+            .get_property(false, root, client_list, AtomEnum::WINDOW, 0, u32::MAX)
+            .ok()
+            .and_then(|c| c.reply().ok());
+        if let Some(reply) = reply {
+            if let Some(windows) = reply.value32() {
+                let windows: Vec<Window> = windows.collect();
+                if !windows.is_empty() {
+                    return windows;
+                }
+            }
+        }
+    }
+
+    let mut ans = Vec::new();
+    collect_children(conn, root, &mut ans);
+    ans
+}
+
+fn find_window(pred: impl Fn(&str, &[String]) -> bool) -> Result<Window, String> {
+    let (conn, root) = connect()?;
+    for window in list_windows(&conn, root) {
+        let title = get_window_title(&conn, window);
+        let class = get_window_class(&conn, window);
+        if pred(&title, &class) {
+            info!("found window {:#x}: title = {}, class = {:?}", window, title, class);
+            return Ok(window);
+        }
+    }
+
+    Err(String::from("cannot find window"))
+}
+
+// the game running under Wine, matched by title or by the executable name Wine uses as class
+fn is_local_window(title: &str, class: &[String]) -> bool {
+    title == "原神"
+        || title == "Genshin Impact"
+        || class.iter().any(|c| {
+            let c = c.to_lowercase();
+            c == "genshinimpact.exe" || c == "yuanshen.exe"
+        })
+}
+
+pub fn find_window_local() -> Result<Window, String> {
+    find_window(is_local_window)
+}
+
+// cloud genshin, in the native client or a browser tab ("云·原神 - Google Chrome")
+pub fn find_window_cloud() -> Result<Window, String> {
+    find_window(|title, _| super::is_cloud_title(title))
+}
+
+pub fn get_client_rect(window: Window) -> Result<PixelRect, String> {
+    let (conn, root) = connect()?;
+    let geometry = conn
+        .get_geometry(window)
+        .map_err(ReplyError::from)
+        .and_then(|c| c.reply())
+        .map_err(|e| format!("cannot get window geometry: {}", e))?;
+    let position = conn
+        .translate_coordinates(window, root, 0, 0)
+        .map_err(ReplyError::from)
+        .and_then(|c| c.reply())
+        .map_err(|e| format!("cannot get window position: {}", e))?;
+
+    Ok(PixelRect {
+        left: position.dst_x as i32,
+        top: position.dst_y as i32,
+        width: geometry.width as i32,
+        height: geometry.height as i32,
+    })
+}
+
+pub fn show_window_and_set_foreground(window: Window) {
+    let (conn, root) = match connect() {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+
+    let _ = conn.map_window(window);
+    // ask the window manager first, EWMH compliant ones ignore direct focus changes
+    if let Ok(active_window) = intern_atom(&conn, "_NET_ACTIVE_WINDOW") {
+        let event = ClientMessageEvent::new(32, window, active_window, [1, CURRENT_TIME, 0, 0, 0]);
+        let _ = conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        );
+    }
+    let _ = conn.configure_window(window, &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE));
+    let _ = conn.set_input_focus(InputFocus::PARENT, window, CURRENT_TIME);
+    let _ = conn.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_class_splits_instance_and_class() {
+        assert_eq!(
+            parse_window_class(b"yuanshen.exe\0YuanShen.exe\0"),
+            vec![String::from("yuanshen.exe"), String::from("YuanShen.exe")]
+        );
+        assert!(parse_window_class(b"").is_empty());
+    }
+
+    #[test]
+    fn local_window_matches_title() {
+        assert!(is_local_window("原神", &[]));
+        assert!(is_local_window("Genshin Impact", &[]));
+        assert!(!is_local_window("原神 - 米游社", &[]));
+    }
+
+    #[test]
+    fn local_window_matches_wine_class() {
+        let class = parse_window_class(b"genshinimpact.exe\0GenshinImpact.exe\0");
+        assert!(is_local_window("", &class));
+        let class = parse_window_class(b"steam.exe\0Steam.exe\0");
+        assert!(!is_local_window("", &class));
+    }
+
+    #[test]
+    fn cloud_window_matches_browser_tab() {
+        assert!(crate::common::utils::is_cloud_title("云·原神 - Google Chrome"));
+        assert!(!crate::common::utils::is_cloud_title("原神"));
+    }
+}
@@ -6,7 +6,7 @@ use std::ptr::null_mut;
 use crate::common::PixelRect;
 use log::{info, warn};
 
-pub use winapi::shared::minwindef::{BOOL, HINSTANCE};
+pub use winapi::shared::minwindef::{BOOL, HINSTANCE, LPARAM};
 pub use winapi::shared::windef::{HWND, POINT as WinPoint, RECT as WinRect};
 pub use winapi::um::libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryA, LoadLibraryW};
 pub use winapi::um::securitybaseapi::{AllocateAndInitializeSid, CheckTokenMembership, FreeSid};
@@ -15,9 +15,9 @@ pub use winapi::um::winnt::{
     SID_IDENTIFIER_AUTHORITY,
 };
 pub use winapi::um::winuser::{
-    ClientToScreen, FindWindowExW, FindWindowW, GetAsyncKeyState, GetClientRect, GetWindowLongPtrW,
-    SetForegroundWindow, SetProcessDPIAware, ShowWindow, GWL_EXSTYLE, GWL_STYLE, SW_RESTORE,
-    VK_RBUTTON,
+    ClientToScreen, EnumWindows, FindWindowExW, FindWindowW, GetAsyncKeyState, GetClientRect,
+    GetWindowLongPtrW, GetWindowTextW, IsWindowVisible, SetForegroundWindow, SetProcessDPIAware,
+    ShowWindow, GWL_EXSTYLE, GWL_STYLE, SW_RESTORE, VK_RBUTTON,
 };
 
 use std::os::windows::ffi::OsStrExt;
@@ -77,6 +77,33 @@ pub fn find_window_cloud() -> Result<HWND, String> {
     Err(String::from("cannot find window"))
 }
 
+unsafe extern "system" fn enum_cloud_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    if IsWindowVisible(hwnd) == 0 {
+        return 1;
+    }
+    let mut buf = [0_u16; 256];
+    let len = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+    if len > 0 && super::is_cloud_title(&String::from_utf16_lossy(&buf[..len as usize])) {
+        *(lparam as *mut HWND) = hwnd;
+        return 0;
+    }
+    1
+}
+
+// cloud genshin in a browser, found by the tab title in the window title
+pub fn find_window_browser_cloud() -> Result<HWND, String> {
+    let mut result: HWND = null_mut();
+    unsafe {
+        EnumWindows(Some(enum_cloud_window), &mut result as *mut HWND as LPARAM);
+    }
+    if result.is_null() {
+        Err(String::from("cannot find window"))
+    } else {
+        info!("found browser cloud window");
+        Ok(result)
+    }
+}
+
 unsafe fn get_client_rect_unsafe(hwnd: HWND) -> Result<PixelRect, String> {
     let mut rect: WinRect = WinRect {
         left: 0,
@@ -0,0 +1,87 @@
+use crate::common::utils;
+use crate::common::PixelRect;
+
+// A running game client, local or cloud, on the current platform.
+pub trait GameWindow: Sized {
+    fn find() -> Result<Self, String>;
+
+    // the client area in screen coordinates
+    fn client_rect(&self) -> Result<PixelRect, String>;
+
+    // restores the window and brings it to the foreground
+    fn focus(&self);
+
+    fn is_cloud(&self) -> bool;
+}
+
+#[cfg(windows)]
+pub struct WindowsGameWindow {
+    hwnd: utils::HWND,
+    is_cloud: bool,
+}
+
+#[cfg(windows)]
+impl GameWindow for WindowsGameWindow {
+    fn find() -> Result<Self, String> {
+        utils::set_dpi_awareness();
+
+        match utils::find_window_local() {
+            Ok(hwnd) => Ok(WindowsGameWindow { hwnd, is_cloud: false }),
+            Err(_) => {
+                let cloud = utils::find_window_cloud().or_else(|_| utils::find_window_browser_cloud());
+                match cloud {
+                    Ok(hwnd) => Ok(WindowsGameWindow { hwnd, is_cloud: true }),
+                    Err(_) => Err(String::from("未找到原神窗口，请确认原神已经开启")),
+                }
+            }
+        }
+    }
+
+    fn client_rect(&self) -> Result<PixelRect, String> {
+        utils::get_client_rect(self.hwnd)
+    }
+
+    fn focus(&self) {
+        utils::show_window_and_set_foreground(self.hwnd);
+    }
+
+    fn is_cloud(&self) -> bool {
+        self.is_cloud
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub struct X11GameWindow {
+    window: utils::Window,
+    is_cloud: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl GameWindow for X11GameWindow {
+    fn find() -> Result<Self, String> {
+        match utils::find_window_local() {
+            Ok(window) => Ok(X11GameWindow { window, is_cloud: false }),
+            Err(_) => match utils::find_window_cloud() {
+                Ok(window) => Ok(X11GameWindow { window, is_cloud: true }),
+                Err(_) => Err(String::from("未找到原神窗口，请确认原神已经开启")),
+            },
+        }
+    }
+
+    fn client_rect(&self) -> Result<PixelRect, String> {
+        utils::get_client_rect(self.window)
+    }
+
+    fn focus(&self) {
+        utils::show_window_and_set_foreground(self.window);
+    }
+
+    fn is_cloud(&self) -> bool {
+        self.is_cloud
+    }
+}
+
+#[cfg(windows)]
+pub type PlatformGameWindow = WindowsGameWindow;
+#[cfg(target_os = "linux")]
+pub type PlatformGameWindow = X11GameWindow;
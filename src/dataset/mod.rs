@@ -0,0 +1,159 @@
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use log::info;
+use serde::Serialize;
+
+use crate::artifact::internal_artifact::{artifact_names_chs, InternalArtifact};
+use crate::common::RawImage;
+
+// samples below this confidence, the probability of the least certain character, are flagged
+// as hard even if the prediction was right
+const HARD_CONFIDENCE: f32 = 0.9;
+
+pub struct DatasetSample {
+    pub field: &'static str,
+    pub image: RawImage,
+    pub prediction: String,
+    pub confidence: f32,
+}
+
+#[derive(Serialize)]
+struct DatasetEntry {
+    file: String,
+    field: &'static str,
+    label: String,
+    prediction: String,
+    confidence: f32,
+    hard: bool,
+}
+
+// Writes the preprocessed field crops of each recognized artifact together with the label
+// derived from the parsed `InternalArtifact`, in the layout consumed by yas-train:
+//
+//   <dir>/images/<index>_<field>.png
+//   <dir>/label.json
+pub struct DatasetWriter {
+    dir: PathBuf,
+    entries: Vec<DatasetEntry>,
+    artifact_count: u32,
+    hard_count: u32,
+}
+
+// the validated text of `field`, or None if the parsed artifact cannot vouch for it
+pub fn field_label(artifact: &InternalArtifact, field: &str, prediction: &str) -> Option<String> {
+    match field {
+        // rebuilt from the set and slot, keeping the spelling read where there are several
+        "title" => {
+            let names = artifact_names_chs(&artifact.set_name, &artifact.slot);
+            if names.contains(&prediction) {
+                Some(String::from(prediction))
+            } else if names.len() == 1 {
+                Some(String::from(names[0]))
+            } else {
+                None
+            }
+        }
+        "main_stat_name" => Some(String::from(artifact.main_stat.name.to_zh_cn())),
+        "main_stat_value" => Some(artifact.main_stat.to_zh_cn_value()),
+        "sub_stat_1" => artifact.sub_stat_1.as_ref().map(|s| s.to_zh_cn_raw()),
+        "sub_stat_2" => artifact.sub_stat_2.as_ref().map(|s| s.to_zh_cn_raw()),
+        "sub_stat_3" => artifact.sub_stat_3.as_ref().map(|s| s.to_zh_cn_raw()),
+        "sub_stat_4" => artifact.sub_stat_4.as_ref().map(|s| s.to_zh_cn_raw()),
+        "level" => Some(format!("+{}", artifact.level)),
+        "equip" => artifact.equip.as_ref().map(|e| format!("{}已装备", e)),
+        _ => None,
+    }
+}
+
+impl DatasetWriter {
+    pub fn new(dir: &str) -> Result<DatasetWriter, String> {
+        let dir = PathBuf::from(dir);
+        if let Err(e) = fs::create_dir_all(dir.join("images")) {
+            return Err(format!("cannot create dataset dir {}: {}", dir.display(), e));
+        }
+
+        Ok(DatasetWriter {
+            dir,
+            entries: Vec::new(),
+            artifact_count: 0,
+            hard_count: 0,
+        })
+    }
+
+    pub fn add(&mut self, artifact: &InternalArtifact, samples: Vec<DatasetSample>) {
+        let index = self.artifact_count;
+        self.artifact_count += 1;
+
+        for sample in samples {
+            let label = match field_label(artifact, sample.field, &sample.prediction) {
+                Some(v) => v,
+                None => continue,
+            };
+            let file = format!("images/{}_{}.png", index, sample.field);
+            if let Err(e) = sample.image.to_gray_image().save(self.dir.join(&file)) {
+                info!("cannot save dataset image {}: {}", file, e);
+                continue;
+            }
+
+            let hard = sample.confidence < HARD_CONFIDENCE || label != sample.prediction;
+            if hard {
+                self.hard_count += 1;
+            }
+            self.entries.push(DatasetEntry {
+                file,
+                field: sample.field,
+                label,
+                prediction: sample.prediction,
+                confidence: sample.confidence,
+                hard,
+            });
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = self.dir.join("label.json");
+        let s = serde_json::to_string_pretty(&self.entries).unwrap();
+        let mut file = match File::create(&path) {
+            Err(why) => return Err(format!("couldn't create {}: {}", path.display(), why)),
+            Ok(file) => file,
+        };
+        if let Err(why) = file.write_all(s.as_bytes()) {
+            return Err(format!("couldn't write to {}: {}", path.display(), why));
+        }
+
+        info!(
+            "dataset: {} samples, {} hard, saved to {}",
+            self.entries.len(),
+            self.hard_count,
+            self.dir.display()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::internal_artifact::test_util::artifact;
+    use crate::artifact::internal_artifact::{ArtifactSetName, ArtifactSlot};
+
+    #[test]
+    fn title_comes_from_the_set_and_slot() {
+        let a = artifact(0, &[]);
+        assert_eq!(field_label(&a, "title", "角斗士的留恋").as_deref(), Some("角斗士的留恋"));
+        assert_eq!(field_label(&a, "title", "角斗士的留蛮").as_deref(), Some("角斗士的留恋"));
+    }
+
+    #[test]
+    fn title_keeps_the_spelling_read() {
+        let mut a = artifact(0, &[]);
+        a.set_name = ArtifactSetName::ArchaicPetra;
+        a.slot = ArtifactSlot::Sand;
+        assert_eq!(field_label(&a, "title", "星罗圭璧之晷").as_deref(), Some("星罗圭璧之晷"));
+        assert_eq!(field_label(&a, "title", "星罗圭壁之晷").as_deref(), Some("星罗圭壁之晷"));
+        assert_eq!(field_label(&a, "title", "星罗圭之晷"), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::Local;
+
+use crate::artifact::internal_artifact::{assign_ids, InternalArtifact};
+use crate::expo::exporter::{self, Exporter, Loss};
+
+pub const DEFAULT_OUTPUT_NAME: &str = "{format}";
+
+// "mona,good" or "all", in the order given, without repeats
+pub fn parse_formats(s: &str) -> Result<Vec<Box<dyn Exporter>>, String> {
+    let mut ans: Vec<Box<dyn Exporter>> = Vec::new();
+    for name in s.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        if name == "all" {
+            return Ok(exporter::exporters());
+        }
+        let e = match exporter::find(name) {
+            Some(v) => v,
+            None => return Err(format!("unknown format {}", name)),
+        };
+        if !ans.iter().any(|v| v.name() == name) {
+            ans.push(e);
+        }
+    }
+    if ans.is_empty() {
+        return Err(String::from("no output format"));
+    }
+
+    Ok(ans)
+}
+
+// local time for `{time}` in output names, e.g. 20230105-213000
+pub fn timestamp() -> String {
+    Local::now().format("%Y%m%d-%H%M%S").to_string()
+}
+
+// Fills in `{format}` and `{time}` in `template` and appends the extension
+pub fn output_file_name(template: &str, exporter: &dyn Exporter, time: &str) -> String {
+    format!(
+        "{}.{}",
+        template
+            .replace("{format}", exporter.name())
+            .replace("{time}", time),
+        exporter.extension()
+    )
+}
+
+// Ids are written as strings, but other tools may use numbers
+pub(crate) fn id_from_json(value: &Option<serde_json::Value>) -> Option<String> {
+    match value {
+        Some(serde_json::Value::String(s)) if !s.is_empty() => Some(s.clone()),
+        Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// Tells the format of an export from its content
+pub fn detect(input: &str) -> Option<Box<dyn Exporter>> {
+    exporter::exporters()
+        .into_iter()
+        .find(|e| e.can_read() && e.detect(input))
+}
+
+// `format` None detects it
+pub fn load(format: Option<&str>, path: &Path) -> Result<Vec<InternalArtifact>, String> {
+    let input = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("cannot read {}: {}", path.display(), e)),
+    };
+    let exporter = match format {
+        Some(name) => exporter::find(name),
+        None => detect(&input),
+    };
+    let mut artifacts = match exporter {
+        Some(e) => e.read(&input)?,
+        None => return Err(format!("unknown format of {}", path.display())),
+    };
+    assign_ids(&mut artifacts);
+    Ok(artifacts)
+}
+
+pub fn save(exporter: &dyn Exporter, artifacts: &[InternalArtifact], path: &Path) -> Result<(), String> {
+    let file = match File::create(path) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("cannot create {}: {}", path.display(), e)),
+    };
+    let mut out = BufWriter::new(file);
+    exporter.write(artifacts, &mut out)?;
+    out.flush()
+        .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+}
+
+// What converting `artifacts`, read from `from`, into `to` drops or makes up
+pub fn losses(from: &dyn Exporter, to: &dyn Exporter, artifacts: &[InternalArtifact]) -> Vec<Loss> {
+    let mut ans = Vec::new();
+    if from.keeps_main_stat_value() && !to.keeps_main_stat_value() {
+        ans.push(Loss {
+            field: "主词条数值",
+            count: artifacts.len(),
+        });
+    }
+    if !from.keeps_main_stat_value() && to.keeps_main_stat_value() {
+        ans.push(Loss {
+            field: "主词条数值（源格式中没有，输出为0）",
+            count: artifacts.len(),
+        });
+    }
+    if from.keeps_equip() && !to.keeps_equip() {
+        ans.push(Loss {
+            field: "装备角色",
+            count: artifacts.iter().filter(|a| a.equip.is_some()).count(),
+        });
+    }
+    ans.extend(to.losses(artifacts));
+
+    ans.into_iter().filter(|l| l.count > 0).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(formats: &[Box<dyn Exporter>]) -> Vec<&'static str> {
+        formats.iter().map(|e| e.name()).collect()
+    }
+
+    #[test]
+    fn parse_formats_keeps_order_and_drops_repeats() {
+        let formats = parse_formats("good, mona,good").unwrap();
+        assert_eq!(names(&formats), vec!["good", "mona"]);
+    }
+
+    #[test]
+    fn parse_formats_all() {
+        assert_eq!(names(&parse_formats("all").unwrap()), exporter::names());
+        assert_eq!(names(&parse_formats("mona,all").unwrap()), exporter::names());
+    }
+
+    #[test]
+    fn parse_formats_rejects_unknown_and_empty() {
+        assert!(parse_formats("mona,foo").is_err());
+        assert!(parse_formats("").is_err());
+        assert!(parse_formats(" , ").is_err());
+    }
+
+    #[test]
+    fn output_file_name_fills_in_the_template() {
+        let good = exporter::find("good").unwrap();
+        let csv = exporter::find("csv").unwrap();
+        assert_eq!(output_file_name(DEFAULT_OUTPUT_NAME, good.as_ref(), "t"), "good.json");
+        assert_eq!(
+            output_file_name("yas-{format}-{time}", csv.as_ref(), "20230105-213000"),
+            "yas-csv-20230105-213000.csv"
+        );
+        assert_eq!(output_file_name("backup", good.as_ref(), "t"), "backup.json");
+    }
+}
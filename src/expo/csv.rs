@@ -0,0 +1,325 @@
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::artifact::internal_artifact::{
+    ArtifactSetName, ArtifactSlot, ArtifactStat, ArtifactStatName, InternalArtifact,
+};
+use crate::expo::exporter::Exporter;
+
+const HEADER: [&str; 16] = [
+    "id",
+    "set",
+    "slot",
+    "rarity",
+    "level",
+    "main_stat",
+    "main_stat_value",
+    "sub_stat_1",
+    "sub_stat_1_value",
+    "sub_stat_2",
+    "sub_stat_2_value",
+    "sub_stat_3",
+    "sub_stat_3_value",
+    "sub_stat_4",
+    "sub_stat_4_value",
+    "equip",
+];
+
+// quotes a field if it would break the row
+fn escape(s: &str) -> String {
+    if s.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        String::from(s)
+    }
+}
+
+// Splits `input` into rows of fields, undoing `escape`. Blank lines are skipped.
+fn parse_rows(input: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                if row.len() > 1 || !row[0].is_empty() {
+                    rows.push(std::mem::take(&mut row));
+                } else {
+                    row.clear();
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(String::from("unterminated quote in csv"));
+    }
+    if !row.is_empty() || !field.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn parse_key<T>(value: Option<T>, kind: &str, key: &str) -> Result<T, String> {
+    match value {
+        Some(v) => Ok(v),
+        None => Err(format!("unknown {} key {}", kind, key)),
+    }
+}
+
+fn parse_number<T: FromStr>(s: &str) -> Result<T, String> {
+    match s.parse() {
+        Ok(v) => Ok(v),
+        Err(_) => Err(format!("invalid number {}", s)),
+    }
+}
+
+fn parse_stat(key: &str, value: &str) -> Result<ArtifactStat, String> {
+    let name = parse_key(ArtifactStatName::from_good(key), "stat", key)?;
+    Ok(ArtifactStat::from_display_value(name, parse_number(value)?))
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    if s.is_empty() {
+        None
+    } else {
+        Some(String::from(s))
+    }
+}
+
+// Full precision, the file is read back by `--since`, `diff` and `convert`. Spreadsheets round
+// it for display.
+fn format_value(stat: &ArtifactStat) -> String {
+    format!("{}", stat.display_value())
+}
+
+// One row per artifact. Sets, slots and stats use the GOOD keys; percentages are written as
+// percent numbers (12.3 for 12.3%), the same as GOOD and MingyuLab.
+pub struct CsvFormat<'a> {
+    artifacts: &'a [InternalArtifact],
+}
+
+impl<'a> CsvFormat<'a> {
+    pub fn new(results: &'a [InternalArtifact]) -> CsvFormat<'a> {
+        CsvFormat { artifacts: results }
+    }
+
+    fn row(artifact: &InternalArtifact) -> Vec<String> {
+        let mut row = vec![
+            artifact.id.clone().unwrap_or_default(),
+            String::from(artifact.set_name.to_good()),
+            String::from(artifact.slot.to_good()),
+            artifact.star.to_string(),
+            artifact.level.to_string(),
+            String::from(artifact.main_stat.name.to_good()),
+            format_value(&artifact.main_stat),
+        ];
+        for stat in [
+            &artifact.sub_stat_1,
+            &artifact.sub_stat_2,
+            &artifact.sub_stat_3,
+            &artifact.sub_stat_4,
+        ]
+        .iter()
+        {
+            match stat {
+                Some(s) => {
+                    row.push(String::from(s.name.to_good()));
+                    row.push(format_value(s));
+                }
+                None => {
+                    row.push(String::new());
+                    row.push(String::new());
+                }
+            }
+        }
+        row.push(artifact.equip.clone().unwrap_or_default());
+        row
+    }
+
+    pub fn to_csv(&self) -> String {
+        // BOM, so Excel reads the character names as UTF-8
+        let mut s = String::from("\u{feff}");
+        s += &HEADER.join(",");
+        s += "\r\n";
+        for artifact in self.artifacts.iter() {
+            let row: Vec<String> = CsvFormat::row(artifact).iter().map(|f| escape(f)).collect();
+            s += &row.join(",");
+            s += "\r\n";
+        }
+        s
+    }
+
+    // reads what `to_csv` writes
+    pub fn parse(input: &str) -> Result<Vec<InternalArtifact>, String> {
+        let mut rows = parse_rows(input.trim_start_matches('\u{feff}'))?.into_iter();
+        match rows.next() {
+            Some(header) if header == HEADER => {}
+            _ => return Err(String::from("not a yas csv file")),
+        }
+
+        rows.enumerate()
+            .map(|(i, row)| {
+                CsvFormat::parse_row(&row).map_err(|e| format!("csv row {}: {}", i + 1, e))
+            })
+            .collect()
+    }
+
+    fn parse_row(row: &[String]) -> Result<InternalArtifact, String> {
+        if row.len() != HEADER.len() {
+            return Err(format!("expected {} fields, got {}", HEADER.len(), row.len()));
+        }
+        // sub stats move up over missing ones, like the other formats
+        let mut sub_stats = row[7..15]
+            .chunks(2)
+            .filter(|s| !s[0].is_empty())
+            .map(|s| parse_stat(&s[0], &s[1]));
+        let mut next_sub_stat = || sub_stats.next().transpose();
+
+        Ok(InternalArtifact {
+            id: non_empty(&row[0]),
+            set_name: parse_key(ArtifactSetName::from_good(&row[1]), "set", &row[1])?,
+            slot: parse_key(ArtifactSlot::from_good(&row[2]), "slot", &row[2])?,
+            star: parse_number(&row[3])?,
+            level: parse_number(&row[4])?,
+            main_stat: parse_stat(&row[5], &row[6])?,
+            sub_stat_1: next_sub_stat()?,
+            sub_stat_2: next_sub_stat()?,
+            sub_stat_3: next_sub_stat()?,
+            sub_stat_4: next_sub_stat()?,
+            equip: non_empty(&row[15]),
+        })
+    }
+}
+
+pub struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn description(&self) -> &'static str {
+        "表格，可用Excel打开"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn write(&self, artifacts: &[InternalArtifact], out: &mut dyn Write) -> Result<(), String> {
+        out.write_all(CsvFormat::new(artifacts).to_csv().as_bytes())
+            .map_err(|e| e.to_string())
+    }
+
+    fn read(&self, input: &str) -> Result<Vec<InternalArtifact>, String> {
+        CsvFormat::parse(input)
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        let header = HEADER.join(",");
+        input.trim_start_matches('\u{feff}').starts_with(header.as_str())
+    }
+
+    fn keeps_main_stat_value(&self) -> bool {
+        true
+    }
+
+    fn keeps_equip(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::diff::KnownArtifacts;
+    use crate::artifact::internal_artifact::test_util::artifact;
+    use crate::artifact::internal_artifact::ArtifactStatName::*;
+
+    #[test]
+    fn plain_fields_are_not_quoted() {
+        assert_eq!(escape("胡桃"), "胡桃");
+        assert_eq!(escape(""), "");
+    }
+
+    #[test]
+    fn fields_that_break_rows_are_quoted() {
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("a\nb"), "\"a\nb\"");
+        assert_eq!(escape("a\rb"), "\"a\rb\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn values_are_written_in_full() {
+        let stat = |name, value| ArtifactStat { name, value };
+        assert_eq!(format_value(&stat(Atk, 18.6)), "18.6");
+        assert_eq!(format_value(&stat(Hp, 4780.0)), "4780");
+        for value in [0.039, 0.0389, 0.1 + 0.2, 0.05832].iter() {
+            let written = format_value(&stat(CriticalDamage, *value));
+            let read = parse_stat("critDMG_", &written).unwrap();
+            // nothing lost beyond the conversion to percent numbers and back
+            assert_eq!(read.value, *value * 100.0 / 100.0, "{}", written);
+        }
+    }
+
+    #[test]
+    fn read_back_artifacts_are_known() {
+        let scanned = vec![
+            artifact(20, &[(Critical, 0.0389), (CriticalDamage, 0.1 + 0.2), (Atk, 18.6)]),
+            artifact(0, &[(AtkPercentage, 0.05832), (Def, 23.15)]),
+        ];
+        let read = CsvFormat::parse(&CsvFormat::new(&scanned).to_csv()).unwrap();
+        let known = KnownArtifacts::new(&read);
+        assert!(scanned.iter().all(|a| known.contains(a)));
+    }
+
+    #[test]
+    fn rows_escape_the_equip() {
+        let mut a = artifact(4, &[(Critical, 0.05)]);
+        a.equip = Some(String::from("a,\"b\""));
+        let csv = CsvFormat::new(&[a]).to_csv();
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.ends_with(",\"a,\"\"b\"\"\""));
+        assert!(row.contains(",critRate_,5,,,"));
+    }
+
+    #[test]
+    fn quoted_fields_are_read_back() {
+        let mut a = artifact(4, &[(Critical, 0.039)]);
+        a.equip = Some(String::from("a,\"b\"\r\nc"));
+        let read = CsvFormat::parse(&CsvFormat::new(&[a]).to_csv()).unwrap();
+        assert_eq!(read[0].equip.as_deref(), Some("a,\"b\"\r\nc"));
+        assert!(read[0].sub_stat_2.is_none());
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        assert!(CsvFormat::parse("a,b\r\n1,2\r\n").is_err());
+        assert!(CsvFormat::parse(&format!("{}\r\n\"x", HEADER.join(","))).is_err());
+        assert!(CsvFormat::parse(&format!("{}\r\nx,y\r\n", HEADER.join(","))).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::Write;
+
+use crate::artifact::internal_artifact::InternalArtifact;
+use crate::expo::csv::CsvExporter;
+use crate::expo::good::GOODExporter;
+use crate::expo::mingyu_lab::MingyuLabExporter;
+use crate::expo::mona_uranai::MonaExporter;
+
+// Information a format cannot hold, and how many artifacts it affects
+#[derive(Debug)]
+pub struct Loss {
+    pub field: &'static str,
+    pub count: usize,
+}
+
+// An output format. To add one, implement this and list it in `exporters`; the command line
+// options and the help text pick it up from there.
+pub trait Exporter {
+    // what `--output-format` calls it
+    fn name(&self) -> &'static str;
+
+    // for the help text
+    fn description(&self) -> &'static str;
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn write(&self, artifacts: &[InternalArtifact], out: &mut dyn Write) -> Result<(), String>;
+
+    // Formats that can be read back parse `input` here, and tell their files apart from
+    // others in `detect`, which gets the file content as well.
+    fn read(&self, _input: &str) -> Result<Vec<InternalArtifact>, String> {
+        Err(format!("{} cannot be read", self.name()))
+    }
+
+    fn can_read(&self) -> bool {
+        false
+    }
+
+    fn detect(&self, _input: &str) -> bool {
+        false
+    }
+
+    fn keeps_main_stat_value(&self) -> bool {
+        false
+    }
+
+    fn keeps_equip(&self) -> bool {
+        false
+    }
+
+    // what else writing `artifacts` drops or changes
+    fn losses(&self, _artifacts: &[InternalArtifact]) -> Vec<Loss> {
+        Vec::new()
+    }
+}
+
+pub fn exporters() -> Vec<Box<dyn Exporter>> {
+    vec![
+        Box::new(MonaExporter),
+        Box::new(MingyuLabExporter),
+        Box::new(GOODExporter),
+        Box::new(CsvExporter),
+    ]
+}
+
+pub fn find(name: &str) -> Option<Box<dyn Exporter>> {
+    exporters().into_iter().find(|e| e.name() == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    exporters().iter().map(|e| e.name()).collect()
+}
+
+pub fn input_names() -> Vec<&'static str> {
+    exporters()
+        .iter()
+        .filter(|e| e.can_read())
+        .map(|e| e.name())
+        .collect()
+}
+
+// e.g. "mona（莫娜占卜铺）、good（GOOD）"
+pub fn help_list() -> String {
+    exporters()
+        .iter()
+        .map(|e| format!("{}（{}）", e.name(), e.description()))
+        .collect::<Vec<_>>()
+        .join("、")
+}
+
+// for `detect` of JSON formats
+pub(crate) fn parse_json(input: &str) -> Option<serde_json::Value> {
+    serde_json::from_str(input).ok()
+}
+
+// serializes `value` as JSON into `out`
+pub(crate) fn write_json<T: serde::Serialize>(value: &T, out: &mut dyn Write) -> Result<(), String> {
+    serde_json::to_writer(out, value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::internal_artifact::test_util::artifact;
+    use crate::artifact::internal_artifact::ArtifactStat;
+    use crate::artifact::internal_artifact::ArtifactStatName::*;
+    use crate::expo::convert;
+
+    fn artifacts() -> Vec<InternalArtifact> {
+        let mut full = artifact(
+            20,
+            &[(Critical, 0.105), (CriticalDamage, 0.218), (AtkPercentage, 0.058), (Def, 23.0)],
+        );
+        full.id = Some(String::from("a1"));
+        full.equip = Some(String::from("胡桃"));
+        let mut three = artifact(0, &[(Recharge, 0.052), (Atk, 19.0), (ElementalMastery, 21.0)]);
+        three.id = Some(String::from("a2"));
+        vec![full, three]
+    }
+
+    fn same_stat(a: &ArtifactStat, b: &ArtifactStat) -> bool {
+        a.name == b.name && (a.value - b.value).abs() < 1e-6
+    }
+
+    fn round_trip(exporter: &dyn Exporter) {
+        let original = artifacts();
+        let mut buf: Vec<u8> = Vec::new();
+        exporter.write(&original, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+
+        let detected = convert::detect(&text).unwrap();
+        assert_eq!(detected.name(), exporter.name());
+        let read = exporter.read(&text).unwrap();
+        assert_eq!(read.len(), original.len());
+
+        for (a, b) in original.iter().zip(read.iter()) {
+            assert_eq!(a.set_name, b.set_name);
+            assert_eq!(a.slot, b.slot);
+            assert_eq!(a.star, b.star);
+            assert_eq!(a.level, b.level);
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.main_stat.name, b.main_stat.name);
+            if exporter.keeps_main_stat_value() {
+                assert!(same_stat(&a.main_stat, &b.main_stat));
+            } else {
+                assert_eq!(b.main_stat.value, 0.0);
+            }
+            if exporter.keeps_equip() {
+                assert_eq!(a.equip, b.equip);
+            }
+            let subs_a: Vec<_> = a.sub_stats().collect();
+            let subs_b: Vec<_> = b.sub_stats().collect();
+            assert_eq!(subs_a.len(), subs_b.len());
+            for (x, y) in subs_a.iter().zip(subs_b.iter()) {
+                assert!(same_stat(x, y), "{:?} != {:?}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        for e in exporters() {
+            assert!(e.can_read(), "{} cannot be read", e.name());
+            round_trip(e.as_ref());
+        }
+    }
+
+    #[test]
+    fn lossy_fields_are_listed() {
+        let artifacts = artifacts();
+        let csv = find("csv").unwrap();
+        let good = find("good").unwrap();
+        let mingyu_lab = find("mingyulab").unwrap();
+        let mona = find("mona").unwrap();
+
+        let fields = |from: &dyn Exporter, to: &dyn Exporter| -> Vec<(&'static str, usize)> {
+            convert::losses(from, to, &artifacts)
+                .iter()
+                .map(|l| (l.field, l.count))
+                .collect()
+        };
+        assert_eq!(
+            fields(csv.as_ref(), good.as_ref()),
+            vec![("主词条数值", 2), ("装备角色", 1)]
+        );
+        assert_eq!(
+            fields(good.as_ref(), csv.as_ref()),
+            vec![("主词条数值（源格式中没有，输出为0）", 2)]
+        );
+        assert_eq!(
+            fields(good.as_ref(), mingyu_lab.as_ref()),
+            vec![("副词条数量（不足4条的以flatATK/0补齐）", 1)]
+        );
+        assert_eq!(
+            fields(mona.as_ref(), good.as_ref()),
+            vec![("主词条数值", 2), ("装备角色", 1)]
+        );
+        assert!(fields(csv.as_ref(), csv.as_ref()).is_empty());
+        assert!(fields(csv.as_ref(), mona.as_ref()).is_empty());
+    }
+}
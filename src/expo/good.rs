@@ -2,9 +2,12 @@ use crate::artifact::internal_artifact::{
     ArtifactSetName, ArtifactSlot, ArtifactStat, ArtifactStatName, InternalArtifact,
 };
 use serde::ser::{SerializeMap, Serializer};
-use serde::Serialize;
-use std::fs::File;
-use std::io::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use strum::IntoEnumIterator;
+
+use crate::expo::convert::id_from_json;
+use crate::expo::exporter::{parse_json, write_json, Exporter};
 
 struct GOODArtifact<'a> {
     artifact: &'a InternalArtifact,
@@ -35,7 +38,10 @@ impl<'a> Serialize for GOODArtifact<'a> {
             substats.push(good_stat)
         }
 
-        let mut root = serializer.serialize_map(Some(8))?;
+        let mut root = serializer.serialize_map(Some(9))?;
+        if let Some(ref id) = artifact.id {
+            root.serialize_entry("id", id)?;
+        }
         root.serialize_entry("setKey", artifact.set_name.to_good())?;
         root.serialize_entry("slotKey", artifact.slot.to_good())?;
         root.serialize_entry("level", &artifact.level)?;
@@ -58,13 +64,7 @@ impl<'a> GOODStat<'a> {
     fn new(stat: &ArtifactStat) -> GOODStat {
         GOODStat {
             key: stat.name.to_good(),
-            value: match stat.name {
-                ArtifactStatName::Atk
-                | ArtifactStatName::ElementalMastery
-                | ArtifactStatName::Hp
-                | ArtifactStatName::Def => stat.value,
-                _ => stat.value * 100.0,
-            }
+            value: stat.display_value(),
         }
     }
 }
@@ -95,7 +95,17 @@ impl ArtifactStatName {
     }
 }
 
+impl ArtifactStatName {
+    pub fn from_good(key: &str) -> Option<ArtifactStatName> {
+        ArtifactStatName::iter().find(|v| v.to_good() == key)
+    }
+}
+
 impl ArtifactSlot {
+    pub fn from_good(key: &str) -> Option<ArtifactSlot> {
+        ArtifactSlot::iter().find(|v| v.to_good() == key)
+    }
+
     pub fn to_good(&self) -> &'static str {
         match self {
             ArtifactSlot::Flower => "flower",
@@ -108,6 +118,10 @@ impl ArtifactSlot {
 }
 
 impl ArtifactSetName {
+    pub fn from_good(key: &str) -> Option<ArtifactSetName> {
+        ArtifactSetName::iter().find(|v| v.to_good() == key)
+    }
+
     pub fn to_good(&self) -> &'static str {
         match self {
             ArtifactSetName::ArchaicPetra => "ArchaicPetra",
@@ -168,7 +182,7 @@ pub struct GOODFormat<'a> {
 }
 
 impl<'a> GOODFormat<'a> {
-    pub fn new(results: &'a Vec<InternalArtifact>) -> GOODFormat {
+    pub fn new(results: &'a [InternalArtifact]) -> GOODFormat<'a> {
         let artifacts: Vec<GOODArtifact<'a>> = results
             .into_iter()
             .map(|artifact| GOODArtifact { artifact })
@@ -180,16 +194,128 @@ impl<'a> GOODFormat<'a> {
             artifacts
         }
     }
+}
 
-    pub fn save(&self, path: String) {
-        let mut file = match File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", path, why),
-            Ok(file) => file,
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GOODArtifactInput {
+    set_key: String,
+    slot_key: String,
+    level: u32,
+    rarity: u32,
+    main_stat_key: String,
+    #[serde(default)]
+    substats: Vec<GOODStatInput>,
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct GOODStatInput {
+    key: String,
+    value: f64,
+}
+
+#[derive(Deserialize)]
+struct GOODInput {
+    format: String,
+    #[serde(default)]
+    artifacts: Vec<GOODArtifactInput>,
+}
+
+impl GOODStatInput {
+    fn to_stat(&self) -> Result<ArtifactStat, String> {
+        let name = match ArtifactStatName::from_good(&self.key) {
+            Some(v) => v,
+            None => return Err(format!("unknown stat key {}", self.key)),
+        };
+        Ok(ArtifactStat::from_display_value(name, self.value))
+    }
+}
+
+impl GOODArtifactInput {
+    // GOOD keeps no main stat value, it is left at 0. `location` is a GOOD character key
+    // rather than a name, so the equip is not kept either.
+    fn to_internal_artifact(&self) -> Result<InternalArtifact, String> {
+        let set_name = match ArtifactSetName::from_good(&self.set_key) {
+            Some(v) => v,
+            None => return Err(format!("unknown set key {}", self.set_key)),
+        };
+        let slot = match ArtifactSlot::from_good(&self.slot_key) {
+            Some(v) => v,
+            None => return Err(format!("unknown slot key {}", self.slot_key)),
+        };
+        let main_stat_name = match ArtifactStatName::from_good(&self.main_stat_key) {
+            Some(v) => v,
+            None => return Err(format!("unknown stat key {}", self.main_stat_key)),
         };
-        let s = serde_json::to_string(&self).unwrap();
-        match file.write_all(s.as_bytes()) {
-            Err(why) => panic!("couldn't write to {}: {}", path, why),
-            _ => {}
+        let mut sub_stats = self.substats.iter().filter(|s| !s.key.is_empty());
+        let mut next_sub_stat = || sub_stats.next().map(|s| s.to_stat()).transpose();
+
+        Ok(InternalArtifact {
+            set_name,
+            slot,
+            star: self.rarity,
+            level: self.level,
+            main_stat: ArtifactStat {
+                name: main_stat_name,
+                value: 0.0,
+            },
+            sub_stat_1: next_sub_stat()?,
+            sub_stat_2: next_sub_stat()?,
+            sub_stat_3: next_sub_stat()?,
+            sub_stat_4: next_sub_stat()?,
+            equip: None,
+            id: id_from_json(&self.id),
+        })
+    }
+}
+
+impl<'a> GOODFormat<'a> {
+    pub fn parse(json: &str) -> Result<Vec<InternalArtifact>, String> {
+        let input: GOODInput = match serde_json::from_str(json) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("invalid GOOD json: {}", e)),
+        };
+        if input.format != "GOOD" {
+            return Err(format!("not a GOOD file (format = {})", input.format));
+        }
+
+        input
+            .artifacts
+            .iter()
+            .map(|a| a.to_internal_artifact())
+            .collect()
+    }
+}
+
+pub struct GOODExporter;
+
+impl Exporter for GOODExporter {
+    fn name(&self) -> &'static str {
+        "good"
+    }
+
+    fn description(&self) -> &'static str {
+        "GOOD，用于Genshin Optimizer等"
+    }
+
+    fn write(&self, artifacts: &[InternalArtifact], out: &mut dyn Write) -> Result<(), String> {
+        write_json(&GOODFormat::new(artifacts), out)
+    }
+
+    fn read(&self, input: &str) -> Result<Vec<InternalArtifact>, String> {
+        GOODFormat::parse(input)
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        match parse_json(input) {
+            Some(v) => v.get("format").and_then(|v| v.as_str()) == Some("GOOD"),
+            None => false,
         }
     }
 }
@@ -2,8 +2,12 @@ use crate::artifact::internal_artifact::{
     ArtifactSetName, ArtifactSlot, ArtifactStat, ArtifactStatName, InternalArtifact,
 };
 use serde::ser::{Serialize, SerializeMap, Serializer};
-use std::fs::File;
-use std::io::prelude::*;
+use serde::Deserialize;
+use std::io::Write;
+use strum::IntoEnumIterator;
+
+use crate::expo::convert::id_from_json;
+use crate::expo::exporter::{parse_json, write_json, Exporter, Loss};
 
 struct MingyuLabArtifact<'a> {
     artifact: &'a InternalArtifact,
@@ -21,17 +25,14 @@ impl<'a> Serialize for MingyuLabArtifact<'a> {
 
         let extract_stat_value = |maybe_stat: &Option<ArtifactStat>| match maybe_stat {
             None => 0.0,
-            Some(stat) => match stat.name {
-                ArtifactStatName::Atk
-                | ArtifactStatName::ElementalMastery
-                | ArtifactStatName::Hp
-                | ArtifactStatName::Def => stat.value,
-                _ => stat.value * 100.0,
-            },
+            Some(stat) => stat.display_value(),
         };
 
         let artifact = &self.artifact;
-        let mut root = serializer.serialize_map(Some(13))?;
+        let mut root = serializer.serialize_map(Some(14))?;
+        if let Some(ref id) = artifact.id {
+            root.serialize_entry("id", id)?;
+        }
         root.serialize_entry("asKey", artifact.set_name.to_mingyu_lab())?;
         root.serialize_entry("rarity", &artifact.star)?;
         root.serialize_entry("slot", artifact.slot.to_mingyu_lab())?;
@@ -75,7 +76,17 @@ impl ArtifactStatName {
     }
 }
 
+impl ArtifactStatName {
+    pub fn from_mingyu_lab(key: &str) -> Option<ArtifactStatName> {
+        ArtifactStatName::iter().find(|v| v.to_mingyu_lab() == key)
+    }
+}
+
 impl ArtifactSlot {
+    pub fn from_mingyu_lab(key: &str) -> Option<ArtifactSlot> {
+        ArtifactSlot::iter().find(|v| v.to_mingyu_lab() == key)
+    }
+
     pub fn to_mingyu_lab(&self) -> &'static str {
         match self {
             ArtifactSlot::Flower => "flower",
@@ -88,6 +99,18 @@ impl ArtifactSlot {
 }
 
 impl ArtifactSetName {
+    pub fn is_supported_by_mingyu_lab(&self) -> bool {
+        *self != ArtifactSetName::Adventurer
+            && *self != ArtifactSetName::LuckyDog
+            && *self != ArtifactSetName::TravelingDoctor
+    }
+
+    pub fn from_mingyu_lab(key: &str) -> Option<ArtifactSetName> {
+        ArtifactSetName::iter()
+            .filter(|v| v.is_supported_by_mingyu_lab())
+            .find(|v| v.to_mingyu_lab() == key)
+    }
+
     pub fn to_mingyu_lab(&self) -> &'static str {
         match self {
             ArtifactSetName::ArchaicPetra => "archaic_petra",
@@ -146,28 +169,144 @@ pub struct MingyuLabFormat<'a> {
 }
 
 impl<'a> MingyuLabFormat<'a> {
-    pub fn new(results: &'a Vec<InternalArtifact>) -> MingyuLabFormat {
+    pub fn new(results: &'a [InternalArtifact]) -> MingyuLabFormat<'a> {
         let artifacts: Vec<MingyuLabArtifact<'a>> = results
             .into_iter()
-            .filter(|artifact| {
-                artifact.set_name != ArtifactSetName::Adventurer
-                    && artifact.set_name != ArtifactSetName::LuckyDog
-                    && artifact.set_name != ArtifactSetName::TravelingDoctor
-            })
+            .filter(|artifact| artifact.set_name.is_supported_by_mingyu_lab())
             .map(|artifact| MingyuLabArtifact { artifact })
             .collect();
         MingyuLabFormat { artifacts }
     }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MingyuLabArtifactInput {
+    as_key: String,
+    rarity: u32,
+    slot: String,
+    level: u32,
+    main_stat: String,
+    sub_stat_1_type: String,
+    sub_stat_1_value: f64,
+    sub_stat_2_type: String,
+    sub_stat_2_value: f64,
+    sub_stat_3_type: String,
+    sub_stat_3_value: f64,
+    sub_stat_4_type: String,
+    sub_stat_4_value: f64,
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+}
+
+// missing sub stats are written as "flatATK" with value 0
+fn sub_stat_from_mingyu_lab(key: &str, value: f64) -> Result<Option<ArtifactStat>, String> {
+    if value == 0.0 {
+        return Ok(None);
+    }
+    let name = match ArtifactStatName::from_mingyu_lab(key) {
+        Some(v) => v,
+        None => return Err(format!("unknown stat {}", key)),
+    };
+    Ok(Some(ArtifactStat::from_display_value(name, value)))
+}
 
-    pub fn save(&self, path: String) {
-        let mut file = match File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", path, why),
-            Ok(file) => file,
+impl MingyuLabArtifactInput {
+    // the main stat value is not part of the format and is left at 0
+    fn to_internal_artifact(&self) -> Result<InternalArtifact, String> {
+        let set_name = match ArtifactSetName::from_mingyu_lab(&self.as_key) {
+            Some(v) => v,
+            None => return Err(format!("unknown set {}", self.as_key)),
         };
-        let s = serde_json::to_string(&self.artifacts).unwrap();
-        match file.write_all(s.as_bytes()) {
-            Err(why) => panic!("couldn't write to {}: {}", path, why),
-            _ => {}
-        }
+        let slot = match ArtifactSlot::from_mingyu_lab(&self.slot) {
+            Some(v) => v,
+            None => return Err(format!("unknown slot {}", self.slot)),
+        };
+        let main_stat_name = match ArtifactStatName::from_mingyu_lab(&self.main_stat) {
+            Some(v) => v,
+            None => return Err(format!("unknown stat {}", self.main_stat)),
+        };
+        // sub stats move up over missing ones, like the other formats
+        let mut sub_stats = vec![
+            sub_stat_from_mingyu_lab(&self.sub_stat_1_type, self.sub_stat_1_value)?,
+            sub_stat_from_mingyu_lab(&self.sub_stat_2_type, self.sub_stat_2_value)?,
+            sub_stat_from_mingyu_lab(&self.sub_stat_3_type, self.sub_stat_3_value)?,
+            sub_stat_from_mingyu_lab(&self.sub_stat_4_type, self.sub_stat_4_value)?,
+        ]
+        .into_iter()
+        .flatten();
+
+        Ok(InternalArtifact {
+            set_name,
+            slot,
+            star: self.rarity,
+            level: self.level,
+            main_stat: ArtifactStat {
+                name: main_stat_name,
+                value: 0.0,
+            },
+            sub_stat_1: sub_stats.next(),
+            sub_stat_2: sub_stats.next(),
+            sub_stat_3: sub_stats.next(),
+            sub_stat_4: sub_stats.next(),
+            equip: None,
+            id: id_from_json(&self.id),
+        })
+    }
+}
+
+impl<'a> MingyuLabFormat<'a> {
+    pub fn parse(json: &str) -> Result<Vec<InternalArtifact>, String> {
+        let input: Vec<MingyuLabArtifactInput> = match serde_json::from_str(json) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("invalid mingyulab json: {}", e)),
+        };
+
+        input.iter().map(|a| a.to_internal_artifact()).collect()
+    }
+}
+
+pub struct MingyuLabExporter;
+
+impl Exporter for MingyuLabExporter {
+    fn name(&self) -> &'static str {
+        "mingyulab"
+    }
+
+    fn description(&self) -> &'static str {
+        "MingyuLab"
+    }
+
+    fn write(&self, artifacts: &[InternalArtifact], out: &mut dyn Write) -> Result<(), String> {
+        write_json(&MingyuLabFormat::new(artifacts).artifacts, out)
+    }
+
+    fn read(&self, input: &str) -> Result<Vec<InternalArtifact>, String> {
+        MingyuLabFormat::parse(input)
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        parse_json(input).map_or(false, |v| v.is_array())
+    }
+
+    fn losses(&self, artifacts: &[InternalArtifact]) -> Vec<Loss> {
+        vec![
+            Loss {
+                field: "套装（冒险家、幸运儿、游医不受支持，整件丢弃）",
+                count: artifacts
+                    .iter()
+                    .filter(|a| !a.set_name.is_supported_by_mingyu_lab())
+                    .count(),
+            },
+            // yas reads the padding back, other tools will see a 0 flatATK sub stat
+            Loss {
+                field: "副词条数量（不足4条的以flatATK/0补齐）",
+                count: artifacts.iter().filter(|a| a.sub_stat_4.is_none()).count(),
+            },
+        ]
     }
 }
@@ -1,3 +1,6 @@
 pub mod mona_uranai;
 pub mod mingyu_lab;
-pub mod good;
\ No newline at end of file
+pub mod good;
+pub mod convert;
+pub mod csv;
+pub mod exporter;
\ No newline at end of file
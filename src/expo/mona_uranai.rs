@@ -1,12 +1,12 @@
 use std::convert::From;
-use std::fs::File;
-use std::io::prelude::*;
-use rand::thread_rng;
-use rand::Rng;
+use std::io::Write;
 
 use serde::ser::{Serialize, Serializer, SerializeMap};
-use tract_onnx::prelude::tract_itertools::Itertools;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
 
+use crate::expo::convert::id_from_json;
+use crate::expo::exporter::{parse_json, write_json, Exporter};
 use crate::artifact::internal_artifact::{ArtifactStatName, ArtifactSetName, ArtifactSlot, InternalArtifact, ArtifactStat};
 
 
@@ -39,7 +39,17 @@ impl ArtifactStatName {
     }
 }
 
+impl ArtifactStatName {
+    pub fn from_mona(key: &str) -> Option<ArtifactStatName> {
+        ArtifactStatName::iter().find(|v| v.to_mona() == key)
+    }
+}
+
 impl ArtifactSetName {
+    pub fn from_mona(key: &str) -> Option<ArtifactSetName> {
+        ArtifactSetName::iter().find(|v| v.to_mona() == key)
+    }
+
     pub fn to_mona(&self) -> String {
         let same = self.to_string();
         let temp = match self {
@@ -87,6 +97,10 @@ impl ArtifactSetName {
 }
 
 impl ArtifactSlot {
+    pub fn from_mona(key: &str) -> Option<ArtifactSlot> {
+        ArtifactSlot::iter().find(|v| v.to_mona() == key)
+    }
+
     pub fn to_mona(&self) -> String {
         let temp = match self {
             ArtifactSlot::Flower => "flower",
@@ -143,20 +157,13 @@ impl Serialize for MonaArtifact {
         root.serialize_entry("star", &self.star);
 
         let equip = match self.equip {
-            Some(ref x) => {
-                if x.contains("已装备") {
-                    let chars = x.chars().collect_vec();
-                    let chars2 = &chars[..chars.len() - 3];
-                    chars2.iter().collect()
-                } else {
-                    String::new()
-                }
-            },
-            None => String::new(),
+            Some(ref x) => x.as_str(),
+            None => "",
         };
-        root.serialize_entry("equip", &equip);
-        let random_id = thread_rng().gen::<u64>();
-        // root.serialize_entry("id", &random_id);
+        root.serialize_entry("equip", equip);
+        if let Some(ref id) = self.id {
+            root.serialize_entry("id", id);
+        }
 
         root.end()
     }
@@ -185,7 +192,7 @@ impl<'a> Serialize for MonaFormat<'a> {
 }
 
 impl<'a> MonaFormat<'a> {
-    pub fn new(results: &Vec<InternalArtifact>) -> MonaFormat {
+    pub fn new(results: &'a [InternalArtifact]) -> MonaFormat<'a> {
         let mut flower: Vec<&MonaArtifact> = Vec::new();
         let mut feather: Vec<&MonaArtifact> = Vec::new();
         let mut cup: Vec<&MonaArtifact> = Vec::new();
@@ -212,17 +219,145 @@ impl<'a> MonaFormat<'a> {
             version: String::from("1"),
         }
     }
+}
+
+#[derive(Deserialize)]
+struct MonaStatInput {
+    name: String,
+    value: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MonaArtifactInput {
+    set_name: String,
+    position: String,
+    main_tag: MonaStatInput,
+    #[serde(default)]
+    normal_tags: Vec<MonaStatInput>,
+    level: u32,
+    star: u32,
+    #[serde(default)]
+    equip: String,
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct MonaInput {
+    #[serde(default)]
+    flower: Vec<MonaArtifactInput>,
+    #[serde(default)]
+    feather: Vec<MonaArtifactInput>,
+    #[serde(default)]
+    sand: Vec<MonaArtifactInput>,
+    #[serde(default)]
+    cup: Vec<MonaArtifactInput>,
+    #[serde(default)]
+    head: Vec<MonaArtifactInput>,
+}
+
+impl MonaStatInput {
+    fn to_stat(&self) -> Result<ArtifactStat, String> {
+        match ArtifactStatName::from_mona(&self.name) {
+            Some(name) => Ok(ArtifactStat {
+                name,
+                value: self.value,
+            }),
+            None => Err(format!("unknown stat name {}", self.name)),
+        }
+    }
+}
 
-    pub fn save(&self, path: String) {
-        let mut file = match File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", path, why),
-            Ok(file) => file,
+impl MonaArtifactInput {
+    fn to_internal_artifact(&self) -> Result<InternalArtifact, String> {
+        let set_name = match ArtifactSetName::from_mona(&self.set_name) {
+            Some(v) => v,
+            None => return Err(format!("unknown set name {}", self.set_name)),
         };
-        let s = serde_json::to_string(&self).unwrap();
+        let slot = match ArtifactSlot::from_mona(&self.position) {
+            Some(v) => v,
+            None => return Err(format!("unknown position {}", self.position)),
+        };
+        let mut sub_stats = self.normal_tags.iter();
+        let mut next_sub_stat = || sub_stats.next().map(|s| s.to_stat()).transpose();
 
-        match file.write_all(s.as_bytes()) {
-            Err(why) => panic!("couldn't write to {}: {}", path, why),
-            _ => {},
+        Ok(InternalArtifact {
+            set_name,
+            slot,
+            star: self.star,
+            level: self.level,
+            main_stat: self.main_tag.to_stat()?,
+            sub_stat_1: next_sub_stat()?,
+            sub_stat_2: next_sub_stat()?,
+            sub_stat_3: next_sub_stat()?,
+            sub_stat_4: next_sub_stat()?,
+            equip: if self.equip.is_empty() {
+                None
+            } else {
+                Some(self.equip.clone())
+            },
+            id: id_from_json(&self.id),
+        })
+    }
+}
+
+impl<'a> MonaFormat<'a> {
+    pub fn parse(json: &str) -> Result<Vec<InternalArtifact>, String> {
+        let input: MonaInput = match serde_json::from_str(json) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("invalid mona json: {}", e)),
+        };
+
+        input
+            .flower
+            .iter()
+            .chain(input.feather.iter())
+            .chain(input.sand.iter())
+            .chain(input.cup.iter())
+            .chain(input.head.iter())
+            .map(|a| a.to_internal_artifact())
+            .collect()
+    }
+}
+
+pub struct MonaExporter;
+
+impl Exporter for MonaExporter {
+    fn name(&self) -> &'static str {
+        "mona"
+    }
+
+    fn description(&self) -> &'static str {
+        "莫娜占卜铺"
+    }
+
+    fn write(&self, artifacts: &[InternalArtifact], out: &mut dyn Write) -> Result<(), String> {
+        write_json(&MonaFormat::new(artifacts), out)
+    }
+
+    fn read(&self, input: &str) -> Result<Vec<InternalArtifact>, String> {
+        MonaFormat::parse(input)
+    }
+
+    fn can_read(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        match parse_json(input).as_ref().and_then(|v| v.as_object()) {
+            Some(root) => ["flower", "feather", "sand", "cup", "head"]
+                .iter()
+                .any(|k| root.contains_key(*k)),
+            None => false,
         }
     }
+
+    fn keeps_main_stat_value(&self) -> bool {
+        true
+    }
+
+    fn keeps_equip(&self) -> bool {
+        true
+    }
 }
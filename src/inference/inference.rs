@@ -10,6 +10,21 @@ use crate::common::utils;
 use image::EncodableLayout;
 
 
+// The highest of one step's scores and its softmax probability. The model outputs raw scores,
+// logits or log probabilities, softmax gives the same for both.
+fn best_word(scores: &[f32]) -> (usize, f32) {
+    let mut max_index = 0;
+    let mut max_value = f32::NEG_INFINITY;
+    for (j, &value) in scores.iter().enumerate() {
+        if value > max_value {
+            max_value = value;
+            max_index = j;
+        }
+    }
+    let sum: f32 = scores.iter().map(|v| (v - max_value).exp()).sum();
+    (max_index, 1.0 / sum)
+}
+
 type ModelType = RunnableModel<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
 
 pub struct CRNNModel {
@@ -59,6 +74,11 @@ impl CRNNModel {
     }
 
     pub fn inference_string(&self, img: &RawImage) -> String {
+        self.inference_string_with_confidence(img).0
+    }
+
+    // confidence is the lowest probability among the emitted characters, 0 to 1
+    pub fn inference_string_with_confidence(&self, img: &RawImage) -> (String, f32) {
         let tensor: Tensor = tract_ndarray::Array4::from_shape_fn((1, 1, 32, 384), |(_, _, y, x)| {
             let index = img.w * y as u32 + x as u32;
             img.data[index as usize]
@@ -71,24 +91,46 @@ impl CRNNModel {
 
         let mut ans = String::new();
         let mut last_word = String::new();
+        let mut confidence: f32 = 1.0;
         for i in 0..shape[0] {
-            let mut max_index = 0;
-            let mut max_value = -1.0;
-            for j in 0..self.index_2_word.len() {
-                let value = arr[[i, 0, j]];
-                if value > max_value {
-                    max_value = value;
-                    max_index = j;
-                }
-            }
+            let scores: Vec<f32> = (0..self.index_2_word.len()).map(|j| arr[[i, 0, j]]).collect();
+            let (max_index, probability) = best_word(&scores);
             let word = &self.index_2_word[max_index];
             if *word != last_word && word != "-" {
                 ans = ans + word;
+                confidence = confidence.min(probability);
             }
 
             last_word = word.clone();
         }
 
-        ans
+        (ans, confidence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn best_word_gives_probabilities() {
+        let (index, p) = best_word(&[1.0, 3.0, 1.0]);
+        assert_eq!(index, 1);
+        let e = 2.0_f32.exp();
+        assert!(close(p, e / (e + 2.0)));
+        assert!(close(best_word(&[0.0, 0.0]).1, 0.5));
+    }
+
+    #[test]
+    fn best_word_takes_log_probabilities_and_negative_logits() {
+        let log = [0.7_f32.ln(), 0.2_f32.ln(), 0.1_f32.ln()];
+        let (index, p) = best_word(&log);
+        assert_eq!(index, 0);
+        assert!(close(p, 0.7));
+        assert_eq!(best_word(&[-5.0, -3.0, -4.0]).0, 1);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,315 @@
+use std::cmp::Ordering;
+
+use image::RgbImage;
+use log::info;
+
+use crate::info::window_info::{
+    Rect, WindowInfo, WINDOW_16_9, WINDOW_43_18, WINDOW_4_3, WINDOW_7_3, WINDOW_8_5,
+};
+
+// luma above which a pixel belongs to the beige panel body / card labels
+const BRIGHT: f64 = 180.0;
+// luma below which a pixel belongs to the dark backpack background
+const DARK: f64 = 70.0;
+
+struct LumaImage {
+    data: Vec<f64>,
+    w: usize,
+    h: usize,
+}
+
+impl LumaImage {
+    fn from_rgb(im: &RgbImage) -> LumaImage {
+        let w = im.width() as usize;
+        let h = im.height() as usize;
+        let data = im
+            .pixels()
+            .map(|p| p[0] as f64 * 0.2989 + p[1] as f64 * 0.5870 + p[2] as f64 * 0.1140)
+            .collect();
+
+        LumaImage { data, w, h }
+    }
+
+    fn get(&self, x: usize, y: usize) -> f64 {
+        self.data[y * self.w + x]
+    }
+
+    // fraction of pixels in column x (rows top..bottom) satisfying `pred`
+    fn column_ratio(&self, x: usize, top: usize, bottom: usize, pred: impl Fn(f64) -> bool) -> f64 {
+        let count = (top..bottom).filter(|&y| pred(self.get(x, y))).count();
+        count as f64 / (bottom - top).max(1) as f64
+    }
+
+    fn row_ratio(&self, y: usize, left: usize, right: usize, pred: impl Fn(f64) -> bool) -> f64 {
+        let count = (left..right).filter(|&x| pred(self.get(x, y))).count();
+        count as f64 / (right - left).max(1) as f64
+    }
+}
+
+// maximal runs [start, end) of indices whose profile value exceeds `threshold`
+fn runs(profile: &[f64], threshold: f64) -> Vec<(usize, usize)> {
+    let mut ans = Vec::new();
+    let mut start = None;
+    for (i, &v) in profile.iter().enumerate() {
+        match (v > threshold, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                ans.push((s, i));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some(s) = start {
+        ans.push((s, profile.len()));
+    }
+
+    ans
+}
+
+fn median(values: &mut Vec<f64>) -> f64 {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    values[values.len() / 2]
+}
+
+// keeps the runs whose length is within `tolerance` of the median length
+fn regular_runs(runs: Vec<(usize, usize)>, tolerance: f64) -> Vec<(usize, usize)> {
+    if runs.is_empty() {
+        return runs;
+    }
+    let mut lens: Vec<f64> = runs.iter().map(|r| (r.1 - r.0) as f64).collect();
+    let m = median(&mut lens);
+    runs.into_iter()
+        .filter(|r| ((r.1 - r.0) as f64 - m).abs() <= m * tolerance)
+        .collect()
+}
+
+// (length, gap) of a sequence of regularly spaced runs
+fn pitch(runs: &[(usize, usize)]) -> (f64, f64) {
+    let mut lens: Vec<f64> = runs.iter().map(|r| (r.1 - r.0) as f64).collect();
+    let len = median(&mut lens);
+    if runs.len() < 2 {
+        return (len, 0.0);
+    }
+    let mut gaps: Vec<f64> = runs.windows(2).map(|p| (p[1].0 - p[0].1) as f64).collect();
+    (len, median(&mut gaps))
+}
+
+fn nearest_reference(w: f64, h: f64) -> &'static WindowInfo {
+    let references: [&'static WindowInfo; 5] =
+        [&WINDOW_43_18, &WINDOW_7_3, &WINDOW_16_9, &WINDOW_8_5, &WINDOW_4_3];
+    let ratio = w / h;
+    let mut best = references[0];
+    for r in references.iter() {
+        if (r.width / r.height - ratio).abs() < (best.width / best.height - ratio).abs() {
+            best = r;
+        }
+    }
+
+    best
+}
+
+struct Located {
+    left: f64,
+    top: f64,
+    right: f64,
+    bottom: f64,
+}
+
+fn locate_panel(im: &LumaImage) -> Result<Located, String> {
+    // the body of the detail panel is the only tall beige block in the right half
+    let top = im.h * 35 / 100;
+    let bottom = im.h * 85 / 100;
+    let profile: Vec<f64> = (0..im.w)
+        .map(|x| im.column_ratio(x, top, bottom, |v| v > BRIGHT))
+        .collect();
+    let (left, right) = runs(&profile, 0.5)
+        .into_iter()
+        .filter(|r| r.0 > im.w / 2)
+        .max_by_key(|r| r.1 - r.0)
+        .ok_or(String::from("未找到圣遗物详情面板"))?;
+
+    let profile: Vec<f64> = (0..im.h)
+        .map(|y| im.row_ratio(y, left, right, |v| v > BRIGHT))
+        .collect();
+    let (body_top, body_bottom) = runs(&profile, 0.5)
+        .into_iter()
+        .max_by_key(|r| r.1 - r.0)
+        .ok_or(String::from("未找到圣遗物详情面板"))?;
+
+    // the coloured header sits right above the body, walk up until the background
+    let mut panel_top = body_top;
+    while panel_top > 0 && im.row_ratio(panel_top - 1, left, right, |v| v > DARK) > 0.8 {
+        panel_top -= 1;
+    }
+
+    Ok(Located {
+        left: left as f64,
+        top: panel_top as f64,
+        right: right as f64,
+        bottom: body_bottom as f64,
+    })
+}
+
+struct Grid {
+    left_margin: f64,
+    top_margin: f64,
+    art_width: f64,
+    art_height: f64,
+    art_gap_x: f64,
+    art_gap_y: f64,
+    art_col: usize,
+    art_row: usize,
+}
+
+fn locate_grid(im: &LumaImage, panel: &Located) -> Result<Grid, String> {
+    let top = panel.top as usize;
+    let bottom = panel.bottom as usize;
+    let right = panel.left as usize;
+
+    let profile: Vec<f64> = (0..right)
+        .map(|x| im.column_ratio(x, top, bottom, |v| v > DARK))
+        .collect();
+    let cols = regular_runs(runs(&profile, 0.5), 0.15);
+    if cols.len() < 2 {
+        return Err(String::from("未找到圣遗物网格"));
+    }
+    let (art_width, art_gap_x) = pitch(&cols);
+
+    let left = cols[0].0;
+    let right = cols[cols.len() - 1].1;
+    let profile: Vec<f64> = (0..im.h)
+        .map(|y| im.row_ratio(y, left, right, |v| v > DARK))
+        .collect();
+    let rows = regular_runs(runs(&profile, 0.5), 0.15);
+    if rows.is_empty() {
+        return Err(String::from("未找到圣遗物网格"));
+    }
+    let (art_height, art_gap_y) = pitch(&rows);
+
+    Ok(Grid {
+        left_margin: left as f64,
+        top_margin: rows[0].0 as f64,
+        art_width,
+        art_height,
+        art_gap_x,
+        art_gap_y,
+        art_col: cols.len(),
+        art_row: rows.len(),
+    })
+}
+
+// Builds a `WindowInfo` for a screenshot of the artifact backpack taken at any window size.
+// The panel and grid are located from brightness profiles, the text regions inside the panel
+// are then placed using the layout of the closest built-in aspect ratio.
+pub fn calibrate(image: &RgbImage) -> Result<WindowInfo, String> {
+    let im = LumaImage::from_rgb(image);
+    let w = im.w as f64;
+    let h = im.h as f64;
+    let reference = nearest_reference(w, h);
+
+    let panel = locate_panel(&im)?;
+    let grid = locate_grid(&im, &panel)?;
+    info!(
+        "panel: ({}, {}) - ({}, {}), grid: {} x {}, card {} x {}",
+        panel.left, panel.top, panel.right, panel.bottom,
+        grid.art_col, grid.art_row, grid.art_width, grid.art_height
+    );
+
+    let ref_panel = &reference.panel_pos;
+    let kx = (panel.right - panel.left) / (ref_panel.1 - ref_panel.3);
+    let ky = (panel.bottom - panel.top) / (ref_panel.2 - ref_panel.0);
+    let px = |x: f64| panel.left + (x - ref_panel.3) * kx;
+    let py = |y: f64| panel.top + (y - ref_panel.0) * ky;
+    let map_rect = |r: &Rect| Rect(py(r.0), px(r.1), py(r.2), px(r.3));
+
+    let gx = (grid.art_width + grid.art_gap_x) / (reference.art_width + reference.art_gap_x);
+    let gy = (grid.art_height + grid.art_gap_y) / (reference.art_height + reference.art_gap_y);
+
+    Ok(WindowInfo {
+        width: w,
+        height: h,
+
+        title_pos: map_rect(&reference.title_pos),
+        main_stat_name_pos: map_rect(&reference.main_stat_name_pos),
+        main_stat_value_pos: map_rect(&reference.main_stat_value_pos),
+        level_pos: map_rect(&reference.level_pos),
+        panel_pos: Rect(panel.top, panel.right, panel.bottom, panel.left),
+
+        sub_stat1_pos: map_rect(&reference.sub_stat1_pos),
+        sub_stat2_pos: map_rect(&reference.sub_stat2_pos),
+        sub_stat3_pos: map_rect(&reference.sub_stat3_pos),
+        sub_stat4_pos: map_rect(&reference.sub_stat4_pos),
+
+        equip_pos: map_rect(&reference.equip_pos),
+        art_count_pos: map_rect(&reference.art_count_pos),
+
+        art_width: grid.art_width,
+        art_height: grid.art_height,
+        art_gap_x: grid.art_gap_x,
+        art_gap_y: grid.art_gap_y,
+
+        art_row: grid.art_row,
+        art_col: grid.art_col,
+
+        left_margin: grid.left_margin,
+        top_margin: grid.top_margin,
+
+        flag_x: grid.left_margin + (reference.flag_x - reference.left_margin) * gx,
+        flag_y: grid.top_margin + (reference.flag_y - reference.top_margin) * gy,
+
+        star_x: px(reference.star_x),
+        star_y: py(reference.star_y),
+
+        pool_pos: map_rect(&reference.pool_pos),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a profile of `count` cards `len` long with `gap` between them, after `start` background
+    fn cards(start: usize, len: usize, gap: usize, count: usize) -> Vec<f64> {
+        let mut ans = vec![0.0; start];
+        for i in 0..count {
+            if i > 0 {
+                ans.extend(vec![0.1; gap]);
+            }
+            ans.extend(vec![0.9; len]);
+        }
+        ans.extend(vec![0.0; 5]);
+        ans
+    }
+
+    #[test]
+    fn runs_above_threshold() {
+        assert_eq!(runs(&cards(3, 10, 4, 3), 0.5), vec![(3, 13), (17, 27), (31, 41)]);
+        assert_eq!(runs(&[0.9, 0.9, 0.1, 0.9], 0.5), vec![(0, 2), (3, 4)]);
+        assert!(runs(&[0.1, 0.2], 0.5).is_empty());
+        assert!(runs(&[], 0.5).is_empty());
+    }
+
+    #[test]
+    fn median_of_unsorted_values() {
+        assert_eq!(median(&mut vec![5.0, 1.0, 3.0]), 3.0);
+        assert_eq!(median(&mut vec![4.0, 2.0, 8.0, 6.0]), 6.0);
+        // a NaN does not panic
+        median(&mut vec![f64::NAN, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn regular_runs_drop_odd_lengths() {
+        let runs = vec![(0, 10), (14, 24), (28, 30), (34, 44), (48, 80)];
+        assert_eq!(regular_runs(runs, 0.15), vec![(0, 10), (14, 24), (34, 44)]);
+        assert!(regular_runs(Vec::new(), 0.15).is_empty());
+    }
+
+    #[test]
+    fn pitch_of_cards() {
+        let cols = regular_runs(runs(&cards(7, 20, 6, 8), 0.5), 0.15);
+        assert_eq!(cols.len(), 8);
+        assert_eq!(pitch(&cols), (20.0, 6.0));
+        assert_eq!(pitch(&cols[..1]), (20.0, 0.0));
+    }
+}
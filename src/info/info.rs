@@ -1,5 +1,8 @@
 use crate::common::{PixelRect, PixelRectBound};
-use crate::info::window_info::{WINDOW_43_18, WINDOW_7_3, WINDOW_16_9, WINDOW_4_3, WINDOW_8_5};
+use log::info;
+
+use crate::info::layout::{Layout, Layouts};
+use crate::info::window_info::{WindowInfo, WINDOW_43_18, WINDOW_7_3, WINDOW_16_9, WINDOW_4_3, WINDOW_8_5};
 
 #[derive(Clone, Debug)]
 pub struct ScanInfo {
@@ -168,20 +171,88 @@ impl ScanInfo {
 }
 
 impl ScanInfo {
+    pub fn from_window_info(window_info: &WindowInfo, rect: &PixelRect) -> ScanInfo {
+        window_info.to_scan_info(rect.height as f64, rect.width as f64, rect.left, rect.top)
+    }
+
     pub fn from_rect(rect: &PixelRect) -> Result<ScanInfo, String> {
-        let mut info: ScanInfo;
-        if rect.height * 16 == rect.width * 9 {
-            info = ScanInfo::from_16_9(rect.width as u32, rect.height as u32, rect.left, rect.top);
-        } else if rect.height * 8 == rect.width * 5 {
-            info = ScanInfo::from_8_5(rect.width as u32, rect.height as u32, rect.left, rect.top);
-        } else if rect.height * 4 == rect.width * 3 {
-            info = ScanInfo::from_4_3(rect.width as u32, rect.height as u32, rect.left, rect.top);
-        } else if rect.height * 7 == rect.width * 3 {
-            info = ScanInfo::from_7_3(rect.width as u32, rect.height as u32, rect.left, rect.top);
-        } else {
-            return Err(String::from("不支持的分辨率"));
+        ScanInfo::from_rect_with_layouts(rect, &Layouts::builtin(), DEFAULT_ASPECT_TOLERANCE)
+    }
+
+    // Picks the layout whose aspect ratio is closest to the window's, as long as the relative
+    // error is within `tolerance`. The layout is applied to the largest centered sub-rect of
+    // exactly its aspect ratio, so a window a few pixels off is treated as letterboxed.
+    pub fn from_rect_with_layouts(rect: &PixelRect, layouts: &Layouts, tolerance: f64) -> Result<ScanInfo, String> {
+        let (layout, error) = match layouts.find_nearest(rect.width, rect.height) {
+            Some(v) => v,
+            None => return Err(String::from("不支持的分辨率")),
+        };
+        if error > tolerance {
+            return Err(format!(
+                "不支持的分辨率（{}x{}，最接近的布局为{}，误差{:.2}%）",
+                rect.width, rect.height, layout.name, error * 100.0
+            ));
         }
 
-        Ok(info)
+        let content = letterbox(rect, layout);
+        info!(
+            "layout {} selected, aspect error {:.3}%, letterbox offset ({}, {})",
+            layout.name,
+            error * 100.0,
+            content.left - rect.left,
+            content.top - rect.top
+        );
+
+        Ok(ScanInfo::from_window_info(&layout.window_info, &content))
     }
-}
\ No newline at end of file
+}
+
+pub const DEFAULT_ASPECT_TOLERANCE: f64 = 0.01;
+
+fn letterbox(rect: &PixelRect, layout: &Layout) -> PixelRect {
+    let ratio = layout.ratio();
+    let width = rect.width.min((rect.height as f64 * ratio).round() as i32);
+    let height = rect.height.min((rect.width as f64 / ratio).round() as i32);
+
+    PixelRect {
+        left: rect.left + (rect.width - width) / 2,
+        top: rect.top + (rect.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, width: i32, height: i32) -> PixelRect {
+        PixelRect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn letterbox_keeps_a_matching_window() {
+        let layout = Layout::new("16:9", WINDOW_16_9);
+        let content = letterbox(&rect(10, 20, 1920, 1080), &layout);
+        assert_eq!((content.left, content.top, content.width, content.height), (10, 20, 1920, 1080));
+    }
+
+    #[test]
+    fn letterbox_centers_a_wider_window() {
+        let layout = Layout::new("16:9", WINDOW_16_9);
+        let content = letterbox(&rect(0, 0, 2000, 1080), &layout);
+        assert_eq!((content.left, content.top, content.width, content.height), (40, 0, 1920, 1080));
+    }
+
+    #[test]
+    fn letterbox_centers_a_taller_window() {
+        let layout = Layout::new("16:9", WINDOW_16_9);
+        let content = letterbox(&rect(0, 0, 1920, 1200), &layout);
+        assert_eq!((content.left, content.top, content.width, content.height), (0, 60, 1920, 1080));
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use log::info;
+
+use crate::info::window_info::{
+    WindowInfo, WINDOW_16_9, WINDOW_43_18, WINDOW_4_3, WINDOW_7_3, WINDOW_8_5,
+};
+
+pub struct Layout {
+    pub name: String,
+    // width : height
+    pub aspect: (u32, u32),
+    pub window_info: WindowInfo,
+}
+
+// All known window layouts. User layouts are searched before the built-in ones, so a layout
+// file can override a built-in aspect ratio as well as add new ones.
+//
+// A layout file is a JSON object keyed by layout name, where a name like "16:9" is also taken
+// as the aspect ratio the layout applies to:
+//
+//   { "16:9": { "width": 1600.0, "height": 900.0, "title_pos": [106.6, 1417.7, 139.6, 1111.8], ... } }
+pub struct Layouts {
+    layouts: Vec<Layout>,
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// e.g "16:9" -> (16, 9)
+pub fn parse_aspect(name: &str) -> Option<(u32, u32)> {
+    let temp: Vec<&str> = name.split(':').collect();
+    if temp.len() != 2 {
+        return None;
+    }
+    let w = temp[0].trim().parse::<u32>().ok()?;
+    let h = temp[1].trim().parse::<u32>().ok()?;
+    if w == 0 || h == 0 {
+        return None;
+    }
+
+    Some((w, h))
+}
+
+// e.g (1920, 1080) -> "16:9"
+pub fn aspect_name(width: u32, height: u32) -> String {
+    let g = gcd(width, height).max(1);
+    format!("{}:{}", width / g, height / g)
+}
+
+fn read_layout_file(path: &str) -> Result<BTreeMap<String, WindowInfo>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("cannot read {}: {}", path, e)),
+    };
+    match serde_json::from_str(&content) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!("cannot parse {}: {}", path, e)),
+    }
+}
+
+// adds (or replaces) the layout `name` in the layout file at `path`
+pub fn save_layout(path: &str, name: &str, window_info: &WindowInfo) -> Result<(), String> {
+    let mut layouts = if Path::new(path).exists() {
+        read_layout_file(path)?
+    } else {
+        BTreeMap::new()
+    };
+    layouts.insert(String::from(name), window_info.clone());
+
+    let mut file = match File::create(path) {
+        Err(why) => return Err(format!("couldn't create {}: {}", path, why)),
+        Ok(file) => file,
+    };
+    let s = serde_json::to_string_pretty(&layouts).unwrap();
+    match file.write_all(s.as_bytes()) {
+        Err(why) => Err(format!("couldn't write to {}: {}", path, why)),
+        _ => Ok(()),
+    }
+}
+
+impl Layout {
+    pub fn new(name: &str, window_info: WindowInfo) -> Layout {
+        let aspect = match parse_aspect(name) {
+            Some(v) => v,
+            None => {
+                let w = window_info.width.round() as u32;
+                let h = window_info.height.round() as u32;
+                let g = gcd(w, h).max(1);
+                (w / g, h / g)
+            }
+        };
+
+        Layout {
+            name: String::from(name),
+            aspect,
+            window_info,
+        }
+    }
+
+    pub fn matches(&self, width: i32, height: i32) -> bool {
+        height as i64 * self.aspect.0 as i64 == width as i64 * self.aspect.1 as i64
+    }
+
+    pub fn ratio(&self) -> f64 {
+        self.aspect.0 as f64 / self.aspect.1 as f64
+    }
+
+    // relative difference between the window's aspect ratio and this layout's
+    pub fn aspect_error(&self, width: i32, height: i32) -> f64 {
+        let ratio = width as f64 / height as f64;
+        (ratio - self.ratio()).abs() / self.ratio()
+    }
+}
+
+impl Layouts {
+    pub fn builtin() -> Layouts {
+        Layouts {
+            layouts: vec![
+                Layout::new("43:18", WINDOW_43_18),
+                Layout::new("16:9", WINDOW_16_9),
+                Layout::new("8:5", WINDOW_8_5),
+                Layout::new("4:3", WINDOW_4_3),
+                Layout::new("7:3", WINDOW_7_3),
+            ],
+        }
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let user = read_layout_file(path)?;
+        info!("loaded {} layout(s) from {}", user.len(), path);
+
+        let mut layouts: Vec<Layout> = user
+            .into_iter()
+            .map(|(name, window_info)| Layout::new(&name, window_info))
+            .collect();
+        layouts.append(&mut self.layouts);
+        self.layouts = layouts;
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Layout> {
+        self.layouts.iter().find(|l| l.name == name)
+    }
+
+    pub fn find_by_aspect(&self, width: i32, height: i32) -> Option<&Layout> {
+        self.layouts.iter().find(|l| l.matches(width, height))
+    }
+
+    // the layout with the closest aspect ratio, together with its relative error
+    pub fn find_nearest(&self, width: i32, height: i32) -> Option<(&Layout, f64)> {
+        let mut ans: Option<(&Layout, f64)> = None;
+        for layout in self.layouts.iter() {
+            let error = layout.aspect_error(width, height);
+            match ans {
+                Some((_, e)) if e <= error => (),
+                _ => ans = Some((layout, error)),
+            }
+        }
+
+        ans
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.layouts.iter().map(|l| l.name.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_aspect_accepts_width_and_height() {
+        assert_eq!(parse_aspect("16:9"), Some((16, 9)));
+        assert_eq!(parse_aspect(" 43 : 18 "), Some((43, 18)));
+    }
+
+    #[test]
+    fn parse_aspect_rejects_other_names() {
+        assert_eq!(parse_aspect("my layout"), None);
+        assert_eq!(parse_aspect("16:9:1"), None);
+        assert_eq!(parse_aspect("16:0"), None);
+        assert_eq!(parse_aspect("a:9"), None);
+    }
+
+    #[test]
+    fn aspect_name_reduces_the_size() {
+        assert_eq!(aspect_name(1920, 1080), "16:9");
+        assert_eq!(aspect_name(2560, 1600), "8:5");
+        assert_eq!(aspect_name(3440, 1440), "43:18");
+    }
+
+    #[test]
+    fn named_layout_takes_the_aspect_from_its_size() {
+        let layout = Layout::new("custom", WINDOW_16_9);
+        assert_eq!(layout.aspect, (16, 9));
+        assert!(layout.matches(1920, 1080));
+        assert!(!layout.matches(1920, 1200));
+    }
+
+    #[test]
+    fn user_layouts_override_builtin_ones() {
+        let path = std::env::temp_dir().join(format!("yas_layouts_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        save_layout(path, "16:9", &WINDOW_8_5).unwrap();
+
+        let mut layouts = Layouts::builtin();
+        layouts.load(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let layout = layouts.find_by_aspect(1920, 1080).unwrap();
+        assert_eq!(layout.window_info.width, WINDOW_8_5.width);
+        assert_eq!(layouts.names().iter().filter(|&&n| n == "16:9").count(), 2);
+    }
+
+    #[test]
+    fn find_nearest_picks_the_closest_aspect() {
+        let layouts = Layouts::builtin();
+
+        let (layout, error) = layouts.find_nearest(1920, 1080).unwrap();
+        assert_eq!(layout.name, "16:9");
+        assert_eq!(error, 0.0);
+
+        // 1366x768 is 1.7786, just off 16:9
+        let (layout, error) = layouts.find_nearest(1366, 768).unwrap();
+        assert_eq!(layout.name, "16:9");
+        assert!(error > 0.0 && error < 0.01);
+
+        let (layout, _) = layouts.find_nearest(1280, 1024).unwrap();
+        assert_eq!(layout.name, "4:3");
+    }
+}
@@ -1,2 +1,4 @@
 pub mod info;
 pub mod window_info;
+pub mod calibration;
+pub mod layout;
\ No newline at end of file
@@ -1,10 +1,13 @@
 use std::borrow::Borrow;
 use std::ops::{Div, Mul};
+use serde::{Deserialize, Serialize};
 use crate::common::PixelRectBound;
 use crate::info::info::ScanInfo;
 
-pub struct Rect(f64, f64, f64, f64); // top, right, bottom, left
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rect(pub f64, pub f64, pub f64, pub f64); // top, right, bottom, left
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WindowInfo {
     pub width: f64,
     pub height: f64,
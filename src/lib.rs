@@ -6,3 +6,4 @@ pub mod scanner;
 pub mod artifact;
 pub mod expo;
 pub mod dto;
+pub mod dataset;
@@ -1,25 +1,34 @@
+use std::fs;
 use std::io::stdin;
 use std::path::Path;
+use std::process;
+use std::str::FromStr;
 use std::time::{Duration, Instant, SystemTime};
 
+use yas::artifact::diff;
+use yas::artifact::score;
+use yas::artifact::internal_artifact::InternalArtifact;
 use yas::capture::{capture_absolute, capture_absolute_image};
+use yas::common::config_file::ConfigFile;
 use yas::common::utils;
 use yas::common::{PixelRect, RawImage};
-use yas::expo::good::GOODFormat;
-use yas::expo::mingyu_lab::MingyuLabFormat;
-use yas::expo::mona_uranai::MonaFormat;
+use yas::expo::convert;
+use yas::expo::exporter;
 use yas::inference::inference::CRNNModel;
 use yas::inference::pre_process::{
     crop, image_to_raw, normalize, pre_process, raw_to_img, to_gray,
 };
-use yas::info::info;
-use yas::scanner::yas_scanner::{YasScanner, YasScannerConfig};
+use yas::common::window::{GameWindow, PlatformGameWindow};
+use yas::info::calibration;
+use yas::info::layout::{aspect_name, save_layout};
+use yas::scanner::scanner::Scanner;
+use yas::scanner::yas_scanner::YasScannerConfig;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use env_logger::{Builder, Env, Target};
 use image::imageops::grayscale;
 use image::{ImageBuffer, Pixel};
-use log::{info, warn, LevelFilter};
+use log::{error, info, warn, LevelFilter};
 use os_info;
 
 fn open_local(path: String) -> RawImage {
@@ -30,18 +39,174 @@ fn open_local(path: String) -> RawImage {
     raw_img
 }
 
-fn main() {
-    Builder::new().filter_level(LevelFilter::Info).init();
+fn error_and_quit(msg: &str) -> ! {
+    error!("{}, 按Enter退出", msg);
+    let mut s: String = String::new();
+    stdin().read_line(&mut s);
+    process::exit(0);
+}
 
-    #[cfg(windows)]
-    if !utils::is_admin() {
-        utils::error_and_quit("请以管理员身份运行该程序")
+fn run_convert(matches: &ArgMatches) {
+    // clap has checked both names
+    let from = exporter::find(matches.value_of("from").unwrap()).unwrap();
+    let to = exporter::find(matches.value_of("to").unwrap()).unwrap();
+    let input = Path::new(matches.value_of("input").unwrap());
+    let output = Path::new(matches.value_of("output").unwrap());
+
+    let artifacts = match convert::load(Some(from.name()), input) {
+        Ok(v) => v,
+        Err(e) => error_and_quit(&format!("读取{}失败：{}", input.display(), e)),
+    };
+    for loss in convert::losses(from.as_ref(), to.as_ref(), &artifacts) {
+        warn!("转换为{}时丢失：{}（{}个圣遗物）", to.name(), loss.field, loss.count);
+    }
+    if let Err(e) = convert::save(to.as_ref(), &artifacts, output) {
+        error_and_quit(&format!("导出失败：{}", e));
     }
+    info!("已将{}个圣遗物转换为{}格式：{}", artifacts.len(), to.name(), output.display());
+}
 
-    if let Some(v) = utils::check_update() {
-        warn!("检测到新版本，请手动更新：{}", v);
+fn describe(artifact: &InternalArtifact) -> String {
+    format!(
+        "{} {:?} {}星 +{} {}",
+        artifact.set_name,
+        artifact.slot,
+        artifact.star,
+        artifact.level,
+        artifact.main_stat.name.to_zh_cn()
+    )
+}
+
+fn describe_sub_stats(artifact: &InternalArtifact) -> String {
+    artifact
+        .sub_stats()
+        .map(|s| s.to_zh_cn_raw())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn run_diff(matches: &ArgMatches) {
+    let format = matches.value_of("format");
+    let load = |name: &str| {
+        let path = Path::new(matches.value_of(name).unwrap());
+        match convert::load(format, path) {
+            Ok(v) => v,
+            Err(e) => error_and_quit(&format!("读取{}失败：{}", path.display(), e)),
+        }
+    };
+    let old = load("old");
+    let new = load("new");
+
+    let d = diff::diff(&old, &new);
+    info!(
+        "新增{}个，移除{}个，升级{}个，未变{}个",
+        d.added.len(),
+        d.removed.len(),
+        d.upgraded.len(),
+        d.unchanged.len()
+    );
+    for a in d.added.iter() {
+        info!("新增：{}  {}", describe(a), describe_sub_stats(a));
+    }
+    for a in d.removed.iter() {
+        info!("移除：{}  {}", describe(a), describe_sub_stats(a));
+    }
+    for u in d.upgraded.iter() {
+        info!(
+            "升级：{} -> +{}  {}",
+            describe(u.old),
+            u.new.level,
+            describe_sub_stats(u.new)
+        );
+    }
+}
+
+fn parse_or_quit<T: FromStr>(matches: &ArgMatches, name: &str, default: T) -> T {
+    match matches.value_of(name) {
+        Some(v) => match v.parse::<T>() {
+            Ok(v) => v,
+            Err(_) => error_and_quit(&format!("参数--{}的值无效：{}", name, v)),
+        },
+        None => default,
+    }
+}
+
+fn run_score(matches: &ArgMatches) {
+    let path = Path::new(matches.value_of("input").unwrap());
+    let artifacts = match convert::load(matches.value_of("format"), path) {
+        Ok(v) => v,
+        Err(e) => error_and_quit(&format!("读取{}失败：{}", path.display(), e)),
+    };
+    let preset = matches.value_of("preset").unwrap_or(score::DEFAULT_PRESET);
+    let weights = match score::StatWeights::load(matches.value_of("weights"), preset) {
+        Ok(v) => v,
+        Err(e) => error_and_quit(&format!(
+            "读取词条权重失败：{}，内置的预设：{}",
+            e,
+            score::preset_names().join(", ")
+        )),
+    };
+    let fodder_below = parse_or_quit(matches, "fodder-below", score::DEFAULT_FODDER_BELOW);
+    let top = parse_or_quit(matches, "top", 20_usize);
+
+    let ranked = score::rank(&artifacts, &weights, fodder_below);
+    let shown = if top == 0 { ranked.len() } else { top.min(ranked.len()) };
+    // format widths count chars, which is off for Chinese, so text columns go through pad
+    info!(
+        "{}  {}  {}  {}  {}  {}  {}  圣遗物",
+        utils::pad("排名", 4, true),
+        utils::pad("双暴", 6, true),
+        utils::pad("词条", 5, true),
+        utils::pad("得分", 5, true),
+        utils::pad("有效率", 6, true),
+        utils::pad("满级", 5, true),
+        utils::pad("狗粮", 4, false)
+    );
+    for (i, (a, s)) in ranked.iter().take(shown).enumerate() {
+        info!(
+            "{:>4}  {:>6.1}  {:>5.1}  {:>5.2}  {:>5.0}%  {:>5.2}  {}  {}  {}",
+            i + 1,
+            s.crit_value,
+            s.rolls,
+            s.score,
+            s.efficiency * 100.0,
+            s.potential,
+            utils::pad(if s.fodder { "是" } else { "" }, 4, false),
+            describe(a),
+            describe_sub_stats(a)
+        );
     }
+    let fodder_count = ranked.iter().filter(|(_, s)| s.fodder).count();
+    info!(
+        "共{}个圣遗物，其中{}个满级预期得分低于{}，可作为狗粮",
+        ranked.len(),
+        fodder_count,
+        fodder_below
+    );
+
+    if let Some(out) = matches.value_of("json") {
+        let s = serde_json::to_string(&score::report(&ranked)).unwrap();
+        match fs::write(out, s) {
+            Ok(_) => info!("评分已写入{}", out),
+            Err(e) => error_and_quit(&format!("写入{}失败：{}", out, e)),
+        }
+    }
+}
 
+fn main() {
+    Builder::new().filter_level(LevelFilter::Info).init();
+
+    let format_names = exporter::names();
+    let input_format_names = exporter::input_names();
+    let output_format_help = format!(
+        "输出格式：{}，多个格式用逗号分隔，all为全部格式（默认为mona）",
+        exporter::help_list()
+    );
+    let preset_help = format!(
+        "词条权重预设（默认为{}），内置：{}",
+        score::DEFAULT_PRESET,
+        score::preset_names().join(", ")
+    );
     let matches = App::new("YAS - 原神圣遗物导出器")
         .version(utils::VERSION)
         .author("wormtql <584130248@qq.com>")
@@ -59,6 +224,12 @@ fn main() {
                 .takes_value(false)
                 .help("输出模型预测结果、二值化图像和灰度图像，debug专用"),
         )
+        .arg(
+            Arg::with_name("dataset")
+                .long("dataset")
+                .takes_value(true)
+                .help("导出带标注的训练数据集到指定目录（供yas-train使用）"),
+        )
         .arg(
             Arg::with_name("capture-only")
                 .long("capture-only")
@@ -89,8 +260,7 @@ fn main() {
                 .long("output-dir")
                 .short("o")
                 .takes_value(true)
-                .help("输出目录")
-                .default_value("."),
+                .help("输出目录（默认为当前目录）"),
         )
         .arg(
             Arg::with_name("scroll-stop")
@@ -109,6 +279,12 @@ fn main() {
                 .long("verbose")
                 .help("显示详细信息"),
         )
+        .arg(
+            Arg::with_name("no-verbose")
+                .long("no-verbose")
+                .conflicts_with("verbose")
+                .help("不显示详细信息（覆盖配置文件中的verbose）"),
+        )
         .arg(
             Arg::with_name("offset-x")
                 .long("offset-x")
@@ -121,167 +297,316 @@ fn main() {
                 .takes_value(true)
                 .help("人为指定纵坐标偏移（截图有偏移时可用该选项校正）"),
         )
+        .arg(
+            Arg::with_name("no-auto-offset")
+                .long("no-auto-offset")
+                .help("不自动检测截图偏移（指定offset-x或offset-y时也不会自动检测）"),
+        )
         .arg(
             Arg::with_name("output-format")
                 .long("output-format")
                 .short("f")
                 .takes_value(true)
-                .help("输出格式")
-                .possible_values(&["mona", "mingyulab", "good"])
-                .default_value("mona"),
+                .help(&output_format_help),
+        )
+        .arg(
+            Arg::with_name("output-name")
+                .long("output-name")
+                .takes_value(true)
+                .help("输出文件名（不含扩展名），{format}替换为格式名，{time}替换为当前时间，如yas-{format}-{time}（默认为{format}）"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .takes_value(true)
+                .help("配置文件（TOML），默认读取用户配置目录下的yas/yas.toml和当前目录下的yas.toml，命令行参数优先"),
+        )
+        .arg(
+            Arg::with_name("calibrate")
+                .long("calibrate")
+                .takes_value(true)
+                .help("校准模式：对背包界面截图，自动定位面板与网格，并以宽高比为键将窗口布局写入指定文件（如layouts.json，默认会读取该文件）"),
+        )
+        .arg(
+            Arg::with_name("layout-file")
+                .long("layout-file")
+                .takes_value(true)
+                .help("自定义窗口布局文件（JSON，以宽高比为键，如\"16:9\"），默认读取当前目录下的layouts.json"),
+        )
+        .arg(
+            Arg::with_name("layout")
+                .long("layout")
+                .takes_value(true)
+                .help("手动指定窗口布局（如16:9，或布局文件中的名字），不再按窗口比例自动选择"),
+        )
+        .arg(
+            Arg::with_name("aspect-tolerance")
+                .long("aspect-tolerance")
+                .takes_value(true)
+                .help("窗口比例与布局比例的最大相对误差，在误差内自动选择最接近的布局（默认为0.01）"),
+        )
+        .arg(
+            Arg::with_name("paging")
+                .long("paging")
+                .takes_value(true)
+                .possible_values(&["wheel", "drag"])
+                .help("翻页方式：wheel为鼠标滚轮（默认），drag为按住背包网格拖动（滚轮翻页不稳定时使用）"),
+        )
+        .arg(
+            Arg::with_name("adaptive-timing")
+                .long("adaptive-timing")
+                .help("根据实际的切换耗时与重复、识别错误情况自动调整翻页停顿和切换等待时间，并保存供下次使用"),
+        )
+        .arg(
+            Arg::with_name("no-adaptive-timing")
+                .long("no-adaptive-timing")
+                .conflicts_with("adaptive-timing")
+                .help("不自动调整时间参数（覆盖配置文件中的adaptive-timing）"),
+        )
+        .arg(
+            Arg::with_name("timing-profile")
+                .long("timing-profile")
+                .takes_value(true)
+                .help("自动调整后的时间参数文件（默认为timing_profile.json）"),
+        )
+        .arg(
+            Arg::with_name("since")
+                .long("since")
+                .takes_value(true)
+                .help("增量扫描：只扫描上次导出（任意导出格式）之后获得的圣遗物，并合并到上次的结果中。需要将背包按入手顺序排序"),
+        )
+        .arg(
+            Arg::with_name("cloud")
+                .long("cloud")
+                .help("按云·原神（串流）处理游戏窗口，用于未能自动识别的云游戏客户端或浏览器"),
+        )
+        .arg(
+            Arg::with_name("no-cloud")
+                .long("no-cloud")
+                .conflicts_with("cloud")
+                .help("不按云·原神处理（覆盖配置文件中的cloud）"),
         )
         .arg(
             Arg::with_name("cloud-wait-switch-artifact")
                 .long("cloud-wait-switch-artifact")
                 .takes_value(true)
-                .help("指定云·原神切换圣遗物等待时间(ms)"),
+                .help("云·原神切换圣遗物时额外的等待时间(ms)，用于抵消串流延迟（默认为300）"),
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("在导出格式之间转换，无需重新扫描")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(&input_format_names)
+                        .help("输入文件的格式"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .required(true)
+                        .possible_values(&format_names)
+                        .help("输出文件的格式"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .required(true)
+                        .index(1)
+                        .help("输入文件"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .required(true)
+                        .index(2)
+                        .help("输出文件"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("比较两次导出，列出新增、移除（被强化消耗或摧毁）和升级的圣遗物")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&input_format_names)
+                        .help("两个文件的格式（默认按内容自动识别）"),
+                )
+                .arg(
+                    Arg::with_name("old")
+                        .required(true)
+                        .index(1)
+                        .help("较早的导出"),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .required(true)
+                        .index(2)
+                        .help("较新的导出"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("score")
+                .about("为圣遗物评分并排序：双暴分、按词条权重计算的有效词条数与满级预期，并标出可作为狗粮的圣遗物")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&input_format_names)
+                        .help("输入文件的格式（默认按内容自动识别）"),
+                )
+                .arg(
+                    Arg::with_name("preset")
+                        .long("preset")
+                        .takes_value(true)
+                        .help(&preset_help),
+                )
+                .arg(
+                    Arg::with_name("weights")
+                        .long("weights")
+                        .takes_value(true)
+                        .help("词条权重文件（JSON，以预设名为键，如角色名，值为GOOD词条名到权重的映射），其中的预设优先于内置预设"),
+                )
+                .arg(
+                    Arg::with_name("top")
+                        .long("top")
+                        .takes_value(true)
+                        .help("显示得分最高的前几个，0为全部（默认为20）"),
+                )
+                .arg(
+                    Arg::with_name("fodder-below")
+                        .long("fodder-below")
+                        .takes_value(true)
+                        .help("满级预期得分低于该值的圣遗物标记为狗粮（默认为3）"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .takes_value(true)
+                        .help("将完整的评分排名写入指定的JSON文件"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .required(true)
+                        .index(1)
+                        .help("导出文件"),
+                ),
         )
         .get_matches();
-    let config = YasScannerConfig::from_match(&matches);
 
-    let rect: PixelRect;
-    let is_cloud: bool;
+    if let Some(matches) = matches.subcommand_matches("convert") {
+        run_convert(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        run_diff(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("score") {
+        run_score(matches);
+        return;
+    }
 
     #[cfg(windows)]
-    {
-        use winapi::shared::windef::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE;
-        use winapi::um::wingdi::{GetDeviceCaps, HORZRES};
-        use winapi::um::winuser::{
-            GetDpiForSystem, GetSystemMetrics, SetForegroundWindow, SetProcessDPIAware,
-            SetThreadDpiAwarenessContext, ShowWindow, SW_RESTORE, SW_SHOW,
-        };
-        // use winapi::um::shellscalingapi::{SetProcessDpiAwareness, PROCESS_PER_MONITOR_DPI_AWARE};
-
-        crate::utils::set_dpi_awareness();
-
-        let hwnd = match utils::find_window_local() {
-            Ok(h) => {
-                is_cloud = false;
-                h
-            }
-            Err(_) => match utils::find_window_cloud() {
-                Ok(h) => {
-                    is_cloud = true;
-                    h
-                }
-                Err(_) => utils::error_and_quit("未找到原神窗口，请确认原神已经开启"),
-            },
-        };
+    if !utils::is_admin() {
+        error_and_quit("请以管理员身份运行该程序")
+    }
 
-        unsafe {
-            ShowWindow(hwnd, SW_RESTORE);
-        }
-        // utils::sleep(1000);
-        unsafe {
-            SetForegroundWindow(hwnd);
-        }
-        utils::sleep(1000);
+    if let Some(v) = utils::check_update() {
+        warn!("检测到新版本，请手动更新：{}", v);
+    }
 
-        rect = utils::get_client_rect(hwnd).unwrap();
+    let config_file = match matches.value_of("config") {
+        Some(path) => ConfigFile::load(Path::new(path)),
+        None => ConfigFile::load_default(),
+    };
+    let config_file = match config_file {
+        Ok(v) => v,
+        Err(e) => error_and_quit(&format!("读取配置文件失败：{}", e)),
+    };
+    let config = match YasScannerConfig::from_match(&matches, &config_file) {
+        Ok(v) => v,
+        Err(e) => error_and_quit(&e),
+    };
+    let output_dir = matches
+        .value_of("output-dir")
+        .map(String::from)
+        .or(config_file.output_dir)
+        .unwrap_or(String::from("."));
+    let output_formats = matches
+        .value_of("output-format")
+        .map(String::from)
+        .or(config_file.output_format)
+        .unwrap_or(String::from("mona"));
+    let output_formats = match convert::parse_formats(&output_formats) {
+        Ok(v) => v,
+        Err(_) => error_and_quit(&format!(
+            "不支持的输出格式：{}，可用的格式：{}, all",
+            output_formats,
+            format_names.join(", ")
+        )),
+    };
+    let output_name = matches
+        .value_of("output-name")
+        .map(String::from)
+        .or(config_file.output_name)
+        .unwrap_or(String::from(convert::DEFAULT_OUTPUT_NAME));
+    if output_formats.len() > 1 && !output_name.contains("{format}") {
+        error_and_quit("输出多个格式时，输出文件名中必须包含{format}");
     }
 
-    #[cfg(all(target_os = "linux"))]
-    {
-        let window_id = unsafe {
-            String::from_utf8_unchecked(
-                std::process::Command::new("sh")
-                    .arg("-c")
-                    .arg(r#" xwininfo|grep "Window id"|cut -d " " -f 4 "#)
-                    .output()
-                    .unwrap()
-                    .stdout,
-            )
+    if let Some(path) = matches.value_of("calibrate") {
+        let window = match PlatformGameWindow::find() {
+            Ok(v) => v,
+            Err(e) => error_and_quit(&e),
         };
-        let window_id = window_id.trim_end_matches("\n");
-
-        let position_size = unsafe {
-            String::from_utf8_unchecked(
-                std::process::Command::new("sh")
-                    .arg("-c")
-                    .arg(&format!(r#" xwininfo -id {window_id}|cut -f 2 -d :|tr -cd "0-9\n"|grep -v "^$"|sed -n "1,2p;5,6p" "#))
-                    .output()
-                    .unwrap()
-                    .stdout,
-            )
+        window.focus();
+        utils::sleep(1000);
+        let rect = match window.client_rect() {
+            Ok(v) => v,
+            Err(e) => error_and_quit(&format!("未能获取窗口大小：{}", e)),
         };
 
-        let mut info = position_size.split("\n");
-
-        let left = info.next().unwrap().parse().unwrap();
-        let top = info.next().unwrap().parse().unwrap();
-        let width = info.next().unwrap().parse().unwrap();
-        let height = info.next().unwrap().parse().unwrap();
-
-        rect = PixelRect {
-            left,
-            top,
-            width,
-            height,
+        let image = match capture_absolute_image(&rect) {
+            Ok(v) => v,
+            Err(e) => error_and_quit(&format!("截图失败：{}", e)),
         };
-        is_cloud = false; // todo: detect cloud genshin by title
-    }
-
-    // rect.scale(1.25);
-    info!(
-        "left = {}, top = {}, width = {}, height = {}",
-        rect.left, rect.top, rect.width, rect.height
-    );
-
-    let mut info: info::ScanInfo;
-    if rect.height * 43 == rect.width * 18 {
-        info =
-            info::ScanInfo::from_43_18(rect.width as u32, rect.height as u32, rect.left, rect.top);
-    } else if rect.height * 16 == rect.width * 9 {
-        info =
-            info::ScanInfo::from_16_9(rect.width as u32, rect.height as u32, rect.left, rect.top);
-    } else if rect.height * 8 == rect.width * 5 {
-        info = info::ScanInfo::from_8_5(rect.width as u32, rect.height as u32, rect.left, rect.top);
-    } else if rect.height * 4 == rect.width * 3 {
-        info = info::ScanInfo::from_4_3(rect.width as u32, rect.height as u32, rect.left, rect.top);
-    } else if rect.height * 7 == rect.width * 3 {
-        info = info::ScanInfo::from_7_3(rect.width as u32, rect.height as u32, rect.left, rect.top);
-    } else {
-        utils::error_and_quit("不支持的分辨率");
+        match calibration::calibrate(&image) {
+            Ok(window_info) => {
+                let name = aspect_name(rect.width as u32, rect.height as u32);
+                match save_layout(path, &name, &window_info) {
+                    Ok(_) => info!("窗口布局 {} 已写入 {}", name, path),
+                    Err(e) => error_and_quit(&e),
+                }
+            }
+            Err(e) => error_and_quit(&format!("校准失败：{}", e)),
+        }
+        return;
     }
 
-    let offset_x = matches
-        .value_of("offset-x")
-        .unwrap_or("0")
-        .parse::<i32>()
-        .unwrap();
-    let offset_y = matches
-        .value_of("offset-y")
-        .unwrap_or("0")
-        .parse::<i32>()
-        .unwrap();
-    info.left += offset_x;
-    info.top += offset_y;
-
-    let mut scanner = YasScanner::new(info.clone(), config, is_cloud);
-
     let now = SystemTime::now();
-    let results = scanner.start();
+    let results = match Scanner::run(config) {
+        Ok(v) => v.artifacts,
+        Err(e) => error_and_quit(&e.to_string()),
+    };
     let t = now.elapsed().unwrap().as_secs_f64();
     info!("time: {}s", t);
 
-    let output_dir = Path::new(matches.value_of("output-dir").unwrap());
-    match matches.value_of("output-format") {
-        Some("mona") => {
-            let output_filename = output_dir.join("mona.json");
-            let mona = MonaFormat::new(&results);
-            mona.save(String::from(output_filename.to_str().unwrap()));
-        }
-        Some("mingyulab") => {
-            let output_filename = output_dir.join("mingyulab.json");
-            let mingyulab = MingyuLabFormat::new(&results);
-            mingyulab.save(String::from(output_filename.to_str().unwrap()));
-        }
-        Some("good") => {
-            let output_filename = output_dir.join("good.json");
-            let good = GOODFormat::new(&results);
-            good.save(String::from(output_filename.to_str().unwrap()));
+    let output_dir = Path::new(&output_dir);
+    let time = convert::timestamp();
+    for exporter in output_formats.iter() {
+        let output_filename = output_dir.join(convert::output_file_name(
+            &output_name,
+            exporter.as_ref(),
+            &time,
+        ));
+        // keep going, the other formats may still be written
+        match convert::save(exporter.as_ref(), &results, &output_filename) {
+            Ok(_) => info!("已导出：{}", output_filename.display()),
+            Err(e) => error!("导出{}失败：{}", exporter.name(), e),
         }
-        _ => unreachable!(),
     }
     // let info = info;
     // let img = info.art_count_position.capture_relative(&info).unwrap();
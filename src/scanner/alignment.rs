@@ -0,0 +1,80 @@
+use log::info;
+
+use crate::capture::frame::FrameGrabber;
+use crate::common::PixelRect;
+use crate::info::info::ScanInfo;
+
+// mean luma step (0-255) a border must reach to be trusted
+const MIN_EDGE_STRENGTH: f64 = 20.0;
+
+pub(crate) struct GrayPatch {
+    data: Vec<f64>,
+    w: i32,
+}
+
+impl GrayPatch {
+    pub(crate) fn get(&self, x: i32, y: i32) -> f64 {
+        self.data[(y * self.w + x) as usize]
+    }
+}
+
+pub(crate) fn capture_gray(grabber: &FrameGrabber, rect: &PixelRect) -> Result<GrayPatch, String> {
+    let raw = grabber.capture(rect)?;
+    let data = raw
+        .chunks(4)
+        .map(|p| p[2] as f64 * 0.2989 + p[1] as f64 * 0.5870 + p[0] as f64 * 0.1140)
+        .collect();
+
+    Ok(GrayPatch { data, w: rect.width })
+}
+
+// the shift in (-radius, radius] maximizing `strength`, with the strength reached
+fn best_shift(radius: i32, strength: impl Fn(i32) -> f64) -> (i32, f64) {
+    let mut best = (0, -1.0);
+    for d in -radius + 1..=radius {
+        let s = strength(d);
+        if s > best.1 {
+            best = (d, s);
+        }
+    }
+
+    best
+}
+
+// Locates the left and top border of the artifact detail panel around where `info` expects
+// it, and returns how far the capture is shifted from the real window content.
+pub fn detect_offset(info: &ScanInfo, grabber: &FrameGrabber) -> Result<(i32, i32), String> {
+    let panel = &info.panel_position;
+    let radius = (info.height as i32 / 30).max(8);
+    let rect = PixelRect {
+        left: info.left + panel.left - radius,
+        top: info.top + panel.top - radius,
+        width: panel.right - panel.left + radius * 2,
+        height: panel.bottom - panel.top + radius * 2,
+    };
+    let im = capture_gray(grabber, &rect)?;
+    let panel_w = panel.right - panel.left;
+    let panel_h = panel.bottom - panel.top;
+
+    // vertical border: sample the middle half of the panel's height
+    let (dx, sx) = best_shift(radius, |d| {
+        let x = radius + d;
+        let ys = radius + panel_h / 4..radius + panel_h * 3 / 4;
+        let n = ys.len().max(1) as f64;
+        ys.map(|y| (im.get(x, y) - im.get(x - 1, y)).abs()).sum::<f64>() / n
+    });
+    // horizontal border: sample the middle half of the panel's width
+    let (dy, sy) = best_shift(radius, |d| {
+        let y = radius + d;
+        let xs = radius + panel_w / 4..radius + panel_w * 3 / 4;
+        let n = xs.len().max(1) as f64;
+        xs.map(|x| (im.get(x, y) - im.get(x, y - 1)).abs()).sum::<f64>() / n
+    });
+    info!("panel border: dx = {} ({:.1}), dy = {} ({:.1})", dx, sx, dy, sy);
+
+    if sx < MIN_EDGE_STRENGTH || sy < MIN_EDGE_STRENGTH {
+        return Err(String::from("未能找到圣遗物详情面板的边框"));
+    }
+
+    Ok((dx, dy))
+}
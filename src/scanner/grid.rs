@@ -0,0 +1,202 @@
+use crate::capture::frame::FrameGrabber;
+use crate::common::PixelRect;
+use crate::info::info::ScanInfo;
+use crate::scanner::alignment::capture_gray;
+
+// mean luma step (0-255) of the card borders for the grid to count as found
+const MIN_BORDER_STRENGTH: f64 = 8.0;
+// and how much they must stand out from the other rows of the strip
+const MIN_BORDER_CONTRAST: f64 = 2.0;
+// a scroll shift must fit this much better than one a row off, or similar cards fooled it
+const MIN_SHIFT_CONTRAST: f64 = 1.5;
+
+// Measures where the artifact grid actually is, from the card borders in a vertical strip
+// through the first column.
+pub struct GridProbe {
+    strip: PixelRect,
+    pitch: i32,
+    card_height: i32,
+}
+
+impl GridProbe {
+    pub fn new(info: &ScanInfo) -> GridProbe {
+        let pitch = (info.art_height + info.art_gap_y) as i32;
+        let height = (pitch * info.art_row as i32).min(info.height as i32 - info.top_margin as i32);
+        GridProbe {
+            strip: PixelRect {
+                left: info.left + (info.left_margin + info.art_width / 4) as i32,
+                top: info.top + info.top_margin as i32,
+                width: (info.art_width / 2).max(1) as i32,
+                height,
+            },
+            pitch,
+            card_height: info.art_height as i32,
+        }
+    }
+
+    // the tolerance within which a row counts as aligned
+    pub fn tolerance(&self) -> i32 {
+        (self.pitch / 8).max(2)
+    }
+
+    pub fn pitch(&self) -> i32 {
+        self.pitch
+    }
+
+    // mean luma of each line of the strip
+    pub fn profile(&self, grabber: &FrameGrabber) -> Option<Vec<f64>> {
+        let im = capture_gray(grabber, &self.strip).ok()?;
+        let w = self.strip.width;
+        Some(
+            (0..self.strip.height)
+                .map(|y| (0..w).map(|x| im.get(x, y)).sum::<f64>() / w as f64)
+                .collect(),
+        )
+    }
+
+    // How far (px) the card tops are below where the layout puts them, in (-pitch/2, pitch/2].
+    // None if no grid is visible in the strip.
+    pub fn measure(&self, grabber: &FrameGrabber) -> Option<i32> {
+        self.phase(&self.profile(grabber)?)
+    }
+
+    fn phase(&self, profile: &[f64]) -> Option<i32> {
+        let h = profile.len() as i32;
+        if h <= self.pitch {
+            return None;
+        }
+
+        let mut edge = vec![0.0; h as usize];
+        for y in 1..h as usize {
+            edge[y] = (profile[y] - profile[y - 1]).abs();
+        }
+        let mean = edge.iter().sum::<f64>() / h as f64;
+
+        // card top and bottom borders repeat every pitch
+        let score = |phase: i32| {
+            let mut sum = 0.0;
+            let mut n = 0;
+            let mut y = phase;
+            while y < h {
+                sum += edge[y as usize];
+                n += 1;
+                let bottom = y + self.card_height;
+                if bottom < h {
+                    sum += edge[bottom as usize];
+                    n += 1;
+                }
+                y += self.pitch;
+            }
+            sum / n.max(1) as f64
+        };
+        let mut best = (0, -1.0);
+        for phase in 0..self.pitch {
+            let s = score(phase);
+            if s > best.1 {
+                best = (phase, s);
+            }
+        }
+
+        let (phase, strength) = best;
+        if strength < MIN_BORDER_STRENGTH || strength < mean * MIN_BORDER_CONTRAST {
+            return None;
+        }
+        if phase > self.pitch / 2 {
+            Some(phase - self.pitch)
+        } else {
+            Some(phase)
+        }
+    }
+
+    // How far (px) the cards moved up from `before` to `after`, found by sliding one profile
+    // over the other with at least a row of overlap. None if no shift fits clearly.
+    pub fn shift(&self, before: &[f64], after: &[f64]) -> Option<i32> {
+        let h = before.len().min(after.len()) as i32;
+        let max = h - self.pitch;
+        if max <= 0 {
+            return None;
+        }
+
+        // mean difference where the shifted profiles overlap
+        let error = |s: i32| {
+            let (from, to) = if s >= 0 { (0, h - s) } else { (-s, h) };
+            let sum: f64 = (from..to)
+                .map(|y| (after[y as usize] - before[(y + s) as usize]).abs())
+                .sum();
+            sum / (to - from) as f64
+        };
+        let errors: Vec<(i32, f64)> = (-max..=max).map(|s| (s, error(s))).collect();
+        let (best, best_error) = errors
+            .iter()
+            .cloned()
+            .fold((0, f64::MAX), |a, b| if b.1 < a.1 { b } else { a });
+        let rival = errors
+            .iter()
+            .filter(|(s, _)| (s - best).abs() >= self.pitch / 2)
+            .map(|&(_, e)| e)
+            .fold(f64::MAX, f64::min);
+
+        // one luma level of slack, so two flat profiles do not count as a match
+        if rival < best_error * MIN_SHIFT_CONTRAST + 1.0 {
+            return None;
+        }
+        Some(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe() -> GridProbe {
+        GridProbe {
+            strip: PixelRect {
+                left: 0,
+                top: 0,
+                width: 1,
+                height: 100,
+            },
+            pitch: 20,
+            card_height: 16,
+        }
+    }
+
+    // the strip scrolled `position` px down the backpack: cards of different brightness on a
+    // dark background, their tops every 20px
+    fn grid(position: i32) -> Vec<f64> {
+        (0..100)
+            .map(|y| {
+                let p = y + position;
+                let card = p.div_euclid(20);
+                if p.rem_euclid(20) < 16 {
+                    100.0 + (card * 37 % 101) as f64
+                } else {
+                    30.0
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn phase_finds_the_card_tops() {
+        let probe = probe();
+        assert_eq!(probe.phase(&grid(0)), Some(0));
+        assert_eq!(probe.phase(&grid(-5)), Some(5));
+        assert_eq!(probe.phase(&grid(4)), Some(-4));
+        assert_eq!(probe.phase(&[50.0; 100]), None);
+    }
+
+    #[test]
+    fn shift_counts_the_rows_moved() {
+        let probe = probe();
+        assert_eq!(probe.shift(&grid(0), &grid(60)), Some(60));
+        assert_eq!(probe.shift(&grid(40), &grid(63)), Some(23));
+        assert_eq!(probe.shift(&grid(60), &grid(0)), Some(-60));
+    }
+
+    #[test]
+    fn shift_gives_up_on_flat_strips() {
+        let probe = probe();
+        assert_eq!(probe.shift(&[50.0; 100], &[50.0; 100]), None);
+    }
+}
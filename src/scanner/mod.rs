@@ -1 +1,7 @@
-pub mod yas_scanner;
\ No newline at end of file
+pub mod yas_scanner;
+pub mod alignment;
+pub mod scanner;
+pub mod timing;
+pub mod switch;
+pub mod grid;
+pub mod paging;
\ No newline at end of file
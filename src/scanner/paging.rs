@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+use enigo::*;
+
+use crate::common::utils;
+
+// How the scanner moves the artifact grid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PagingMode {
+    // mouse wheel, the default
+    Wheel,
+    // press on the grid and drag it up, for clients where wheel deltas are unreliable
+    Drag,
+}
+
+impl FromStr for PagingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "wheel" => Ok(PagingMode::Wheel),
+            "drag" => Ok(PagingMode::Drag),
+            _ => Err(format!("未知的翻页方式：{}（可选wheel、drag）", s)),
+        }
+    }
+}
+
+// pixels moved between two drag events
+const DRAG_STEP: i32 = 10;
+
+// Splits a drag of `distance` px into drags of at most `max` px each
+pub fn split_drag(distance: i32, max: i32) -> Vec<i32> {
+    let max = max.max(1);
+    let mut ans = Vec::new();
+    let mut left = distance;
+    while left > 0 {
+        ans.push(left.min(max));
+        left -= max;
+    }
+    ans
+}
+
+// Drags from (x, from_y) to (x, to_y) with the left button held. The pointer rests at the end
+// before the release so the grid does not keep sliding.
+pub fn drag(enigo: &mut Enigo, x: i32, from_y: i32, to_y: i32) {
+    enigo.mouse_move_to(x, from_y);
+    utils::sleep(20);
+    enigo.mouse_down(MouseButton::Left);
+    utils::sleep(20);
+
+    let direction = if to_y < from_y { -1 } else { 1 };
+    let mut y = from_y;
+    while (to_y - y) * direction > 0 {
+        y += direction * DRAG_STEP.min((to_y - y).abs());
+        enigo.mouse_move_to(x, y);
+        utils::sleep(5);
+    }
+
+    utils::sleep(150);
+    enigo.mouse_up(MouseButton::Left);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_drag_is_not_split() {
+        assert_eq!(split_drag(300, 400), vec![300]);
+        assert_eq!(split_drag(400, 400), vec![400]);
+    }
+
+    #[test]
+    fn long_drag_is_split() {
+        assert_eq!(split_drag(1000, 400), vec![400, 400, 200]);
+        assert!(split_drag(0, 400).is_empty());
+    }
+}
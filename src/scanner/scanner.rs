@@ -0,0 +1,90 @@
+use std::error::Error;
+use std::fmt;
+
+use log::info;
+
+use crate::artifact::internal_artifact::InternalArtifact;
+use crate::common::utils;
+use crate::common::window::{GameWindow, PlatformGameWindow};
+use crate::scanner::yas_scanner::{YasScanner, YasScannerConfig};
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub artifacts: Vec<InternalArtifact>,
+    // artifacts that could not be parsed from the recognized text
+    pub error_count: u32,
+    pub dup_count: u32,
+}
+
+#[derive(Debug)]
+pub enum ScanError {
+    // invalid options
+    Config(String),
+    // the game window cannot be found or measured
+    Window(String),
+    // no layout fits the window
+    Layout(String),
+    // the window content cannot be located, usually the backpack is not open
+    Align(String),
+    // the screen cannot be captured
+    Capture(String),
+    // the artifact count was read but cannot be made sense of
+    Count(String),
+    // captures or dumps cannot be written
+    Io(String),
+    // the recognition thread died
+    Recognition(String),
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::Config(e) => write!(f, "配置错误：{}", e),
+            ScanError::Window(e) => write!(f, "{}", e),
+            ScanError::Layout(e) => write!(f, "{}", e),
+            ScanError::Align(e) => write!(f, "无法自动对齐原神窗口：{}。请确认已打开背包的圣遗物页面，或使用--offset-x/--offset-y手动指定偏移", e),
+            ScanError::Capture(e) => write!(f, "截图失败：{}", e),
+            ScanError::Count(e) => write!(f, "无法识别圣遗物数量“{}”，请用--number指定", e),
+            ScanError::Io(e) => write!(f, "{}", e),
+            ScanError::Recognition(e) => write!(f, "识别线程异常退出：{}", e),
+        }
+    }
+}
+
+impl Error for ScanError {}
+
+// Library entry point: finds the game window, works out its layout and scans the whole
+// backpack. Nothing here reads stdin or exits the process, errors are returned to the caller.
+pub struct Scanner;
+
+impl Scanner {
+    pub fn run(config: YasScannerConfig) -> Result<ScanReport, ScanError> {
+        config.validate().map_err(ScanError::Config)?;
+
+        let window = PlatformGameWindow::find().map_err(ScanError::Window)?;
+        window.focus();
+        utils::sleep(1000);
+
+        let rect = match window.client_rect() {
+            Ok(v) => v,
+            Err(e) => return Err(ScanError::Window(format!("未能获取窗口大小：{}", e))),
+        };
+        info!(
+            "left = {}, top = {}, width = {}, height = {}",
+            rect.left, rect.top, rect.width, rect.height
+        );
+
+        let info = config.scan_info(&rect).map_err(ScanError::Layout)?;
+        let auto_offset = config.auto_offset;
+        let is_cloud = config.cloud || window.is_cloud();
+        if is_cloud {
+            info!("cloud client, waits follow the stream latency");
+        }
+        let mut scanner = YasScanner::new(info, config, is_cloud).map_err(ScanError::Capture)?;
+        if auto_offset {
+            scanner.auto_align().map_err(ScanError::Align)?;
+        }
+
+        scanner.start()
+    }
+}
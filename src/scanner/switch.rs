@@ -0,0 +1,205 @@
+use std::time::Instant;
+
+use crate::capture::frame::{Frame, FrameGrabber};
+use crate::common::{utils, PixelRect};
+
+// fingerprint resolution, in cells
+const GRID_W: usize = 16;
+const GRID_H: usize = 12;
+// ms between grabs, so polling does not take a whole core
+const POLL_INTERVAL: u32 = 5;
+// grabs failing in a row before the screen is taken as gone
+const MAX_GRAB_FAILURES: u32 = 20;
+
+// A downscaled color thumbnail of a capture: the mean B, G and R of each grid cell.
+#[derive(Clone, Debug)]
+pub struct Fingerprint {
+    cells: Vec<[f64; 3]>,
+}
+
+impl Fingerprint {
+    // `data` is BGRA, `w` * `h` pixels
+    pub fn from_bgra(data: &[u8], w: usize, h: usize) -> Fingerprint {
+        let mut cells = vec![[0.0; 3]; GRID_W * GRID_H];
+        let mut counts = vec![0_u32; GRID_W * GRID_H];
+        for y in 0..h {
+            let cy = y * GRID_H / h;
+            for x in 0..w {
+                let cx = x * GRID_W / w;
+                let p = &data[(y * w + x) * 4..];
+                let cell = &mut cells[cy * GRID_W + cx];
+                cell[0] += p[0] as f64;
+                cell[1] += p[1] as f64;
+                cell[2] += p[2] as f64;
+                counts[cy * GRID_W + cx] += 1;
+            }
+        }
+        for (cell, &count) in cells.iter_mut().zip(counts.iter()) {
+            let n = count.max(1) as f64;
+            for c in cell.iter_mut() {
+                *c /= n;
+            }
+        }
+
+        Fingerprint { cells }
+    }
+
+    pub fn from_frame(frame: &Frame, rect: &PixelRect) -> Fingerprint {
+        Fingerprint::from_bgra(
+            &frame.crop(rect),
+            rect.width as usize,
+            rect.height as usize,
+        )
+    }
+
+    // The largest difference of a cell, as the mean absolute difference of its channels,
+    // 0-255. Look-alike artifacts can differ in a single digit, which only shows in one or
+    // two cells, so it is not averaged over the whole panel.
+    pub fn distance(&self, other: &Fingerprint) -> f64 {
+        self.cells
+            .iter()
+            .zip(other.cells.iter())
+            .map(|(a, b)| {
+                ((a[0] - b[0]).abs() + (a[1] - b[1]).abs() + (a[2] - b[2]).abs()) / 3.0
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+// Decides when the detail panel shows a new artifact: the panel must differ from the last
+// accepted artifact and then stay unchanged for `stable_frames` consecutive frames, so the
+// switch animation and half-drawn frames are not taken for a result.
+pub struct SwitchDetector {
+    previous: Option<Fingerprint>,
+    // the frame the last wait ended on
+    frame: Option<Frame>,
+    stable_frames: u32,
+    // cell differences up to this are noise (video compression on the cloud)
+    noise: f64,
+}
+
+impl SwitchDetector {
+    pub fn new(stable_frames: u32, noise: f64) -> SwitchDetector {
+        SwitchDetector {
+            previous: None,
+            frame: None,
+            stable_frames,
+            noise,
+        }
+    }
+
+    pub fn local() -> SwitchDetector {
+        SwitchDetector::new(2, 1.0)
+    }
+
+    pub fn cloud() -> SwitchDetector {
+        SwitchDetector::new(3, 6.0)
+    }
+
+    // Grabs frames of `area` until `rect` in it shows a new, settled artifact, and returns the
+    // milliseconds it took. Gives up after `max_wait` ms; the last frame is then taken as the current artifact
+    // so an identical looking neighbour does not stall every later switch. Fails if the screen
+    // cannot be grabbed several times in a row.
+    pub fn wait(
+        &mut self,
+        grabber: &FrameGrabber,
+        area: &PixelRect,
+        rect: &PixelRect,
+        max_wait: u32,
+    ) -> Result<Option<u128>, String> {
+        let now = Instant::now();
+        let mut last: Option<Fingerprint> = None;
+        let mut changed = false;
+        let mut stable = 0;
+        let mut failures = 0;
+
+        while now.elapsed().as_millis() < max_wait as u128 {
+            let image = match grabber.grab_rect(area) {
+                Ok(v) => v,
+                Err(e) => {
+                    failures += 1;
+                    if failures >= MAX_GRAB_FAILURES {
+                        return Err(e);
+                    }
+                    utils::sleep(POLL_INTERVAL);
+                    continue;
+                }
+            };
+            failures = 0;
+            let frame = Fingerprint::from_frame(&image, rect);
+            self.frame = Some(image);
+
+            if !changed {
+                changed = match self.previous {
+                    Some(ref p) => frame.distance(p) > self.noise,
+                    None => true,
+                };
+            }
+            stable = match last {
+                Some(ref l) if frame.distance(l) <= self.noise => stable + 1,
+                _ => 0,
+            };
+            last = Some(frame);
+
+            if changed && stable >= self.stable_frames {
+                self.previous = last;
+                return Ok(Some(now.elapsed().as_millis()));
+            }
+            utils::sleep(POLL_INTERVAL);
+        }
+
+        if last.is_some() {
+            self.previous = last;
+        }
+        Ok(None)
+    }
+
+    // the frame the last `wait` ended on, so the panel is read from what was checked
+    pub fn take_frame(&mut self) -> Option<Frame> {
+        self.frame.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: usize = 160;
+    const H: usize = 120;
+
+    fn panel() -> Vec<u8> {
+        let mut data = vec![0_u8; W * H * 4];
+        for (i, p) in data.chunks_mut(4).enumerate() {
+            let v = ((i % W) + (i / W)) as u8;
+            p.copy_from_slice(&[v, v / 2, 255 - v, 255]);
+        }
+        data
+    }
+
+    // a 4x5 pixel patch turned white, e.g. one digit of a sub stat
+    fn with_digit(mut data: Vec<u8>, x: usize, y: usize) -> Vec<u8> {
+        for dy in 0..5 {
+            for dx in 0..4 {
+                let i = ((y + dy) * W + x + dx) * 4;
+                data[i..i + 3].copy_from_slice(&[255, 255, 255]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn same_panel_has_no_distance() {
+        let a = Fingerprint::from_bgra(&panel(), W, H);
+        let b = Fingerprint::from_bgra(&panel(), W, H);
+        assert_eq!(a.distance(&b), 0.0);
+    }
+
+    #[test]
+    fn one_changed_cell_is_a_switch() {
+        let a = Fingerprint::from_bgra(&panel(), W, H);
+        let b = Fingerprint::from_bgra(&with_digit(panel(), 102, 83), W, H);
+        let d = a.distance(&b);
+        assert!(d > SwitchDetector::local().noise, "{}", d);
+        assert!(d > SwitchDetector::cloud().noise, "{}", d);
+    }
+}
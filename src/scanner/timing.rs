@@ -0,0 +1,292 @@
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_TIMING_PROFILE: &str = "timing_profile.json";
+pub const DEFAULT_SCROLL_STOP: u32 = 80;
+pub const DEFAULT_MAX_WAIT_SWITCH_ARTIFACT: u32 = 800;
+
+const MIN_SCROLL_STOP: f64 = 20.0;
+const MAX_SCROLL_STOP: f64 = 500.0;
+const MIN_SWITCH_WAIT: f64 = 100.0;
+const MAX_SWITCH_WAIT: f64 = 3000.0;
+
+// latency beyond this is taken for a hiccup, not the stream
+const MAX_LATENCY: f64 = 1000.0;
+// the switch wait aims at this multiple of the average switch time
+const SWITCH_WAIT_MARGIN: f64 = 3.0;
+// and the scroll stop at this multiple of the average time the grid takes to settle
+const SCROLL_STOP_MARGIN: f64 = 1.5;
+// weight of a new scroll settle time in its moving average
+const SETTLE_WEIGHT: f64 = 0.2;
+// waits are multiplied by this after a problem
+const BACK_OFF: f64 = 1.5;
+// fraction of the distance to the target covered by each successful step
+const TIGHTEN: f64 = 0.1;
+// successful steps after a back off before waits are tightened again
+const COOLDOWN: u32 = 20;
+
+// Learned timings, saved between runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimingProfile {
+    pub scroll_stop: u32,
+    pub max_wait_switch_artifact: u32,
+    pub avg_switch_time: f64,
+}
+
+impl TimingProfile {
+    pub fn load(path: &str) -> Result<TimingProfile, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("cannot read {}: {}", path, e)),
+        };
+        match serde_json::from_str(&content) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(format!("cannot parse {}: {}", path, e)),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut file = match File::create(path) {
+            Err(why) => return Err(format!("couldn't create {}: {}", path, why)),
+            Ok(file) => file,
+        };
+        let s = serde_json::to_string_pretty(self).unwrap();
+        match file.write_all(s.as_bytes()) {
+            Err(why) => Err(format!("couldn't write to {}: {}", path, why)),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Tunes the scroll stop and the artifact switch wait while scanning. The scroll stop follows
+// the measured time the grid takes to settle, the switch wait shrinks slowly towards what the
+// game actually needs, and both grow quickly when duplicates, recognition errors or timeouts
+// show they are too short. With `adaptive` off the configured values are used as they are.
+pub struct Timing {
+    adaptive: bool,
+    scroll_stop: f64,
+    switch_wait: f64,
+    avg_switch_time: f64,
+    switch_count: u32,
+    // moving average of how long the grid takes to stop after a scroll
+    scroll_settle: Option<f64>,
+    // fastest switch seen, a bound on the input to screen round trip
+    latency: Option<f64>,
+    cooldown: u32,
+}
+
+fn clamp(v: f64, min: f64, max: f64) -> f64 {
+    v.max(min).min(max)
+}
+
+impl Timing {
+    pub fn fixed(scroll_stop: u32, max_wait_switch_artifact: u32) -> Timing {
+        Timing {
+            adaptive: false,
+            scroll_stop: scroll_stop as f64,
+            switch_wait: max_wait_switch_artifact as f64,
+            avg_switch_time: 0.0,
+            switch_count: 0,
+            scroll_settle: None,
+            latency: None,
+            cooldown: 0,
+        }
+    }
+
+    // Starts from the given values, and from the saved profile at `path` for those that were
+    // not given.
+    pub fn adaptive(
+        scroll_stop: Option<u32>,
+        max_wait_switch_artifact: Option<u32>,
+        path: &str,
+    ) -> Timing {
+        let mut timing = Timing::fixed(
+            scroll_stop.unwrap_or(DEFAULT_SCROLL_STOP),
+            max_wait_switch_artifact.unwrap_or(DEFAULT_MAX_WAIT_SWITCH_ARTIFACT),
+        );
+        timing.adaptive = true;
+
+        if Path::new(path).exists() {
+            match TimingProfile::load(path) {
+                Ok(profile) => {
+                    info!("loaded timing profile from {}: {:?}", path, profile);
+                    if scroll_stop.is_none() {
+                        timing.scroll_stop =
+                            clamp(profile.scroll_stop as f64, MIN_SCROLL_STOP, MAX_SCROLL_STOP);
+                    }
+                    if max_wait_switch_artifact.is_none() {
+                        timing.switch_wait = clamp(
+                            profile.max_wait_switch_artifact as f64,
+                            MIN_SWITCH_WAIT,
+                            MAX_SWITCH_WAIT,
+                        );
+                    }
+                    // counts as one sample, so this run's measurements take over quickly
+                    timing.avg_switch_time = profile.avg_switch_time;
+                    timing.switch_count = 1;
+                }
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        timing
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    pub fn scroll_stop(&self) -> u32 {
+        self.scroll_stop.round() as u32
+    }
+
+    // how long a scroll is watched for, so slow ones are measured rather than cut off
+    pub fn max_scroll_stop(&self) -> u32 {
+        MAX_SCROLL_STOP as u32
+    }
+
+    pub fn max_wait_switch_artifact(&self) -> u32 {
+        self.switch_wait.round() as u32
+    }
+
+    // an artifact switch was seen after `ms`
+    pub fn on_switch(&mut self, ms: f64) {
+        self.avg_switch_time = (self.avg_switch_time * self.switch_count as f64 + ms)
+            / (self.switch_count as f64 + 1.0);
+        self.switch_count += 1;
+        self.latency = Some(match self.latency {
+            Some(v) => v.min(ms),
+            None => ms,
+        });
+        if !self.adaptive {
+            return;
+        }
+
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return;
+        }
+        let target = clamp(
+            self.avg_switch_time * SWITCH_WAIT_MARGIN,
+            MIN_SWITCH_WAIT,
+            MAX_SWITCH_WAIT,
+        );
+        if target < self.switch_wait {
+            self.switch_wait += (target - self.switch_wait) * TIGHTEN;
+        }
+    }
+
+    // the grid settled `ms` after a scroll
+    pub fn on_scroll(&mut self, ms: f64) {
+        let settle = match self.scroll_settle {
+            Some(v) => v + (ms - v) * SETTLE_WEIGHT,
+            None => ms,
+        };
+        self.scroll_settle = Some(settle);
+        if !self.adaptive {
+            return;
+        }
+
+        let target = clamp(settle * SCROLL_STOP_MARGIN, MIN_SCROLL_STOP, MAX_SCROLL_STOP);
+        // right after a back off only slower scrolls are followed
+        if target > self.scroll_stop || self.cooldown == 0 {
+            self.scroll_stop = target;
+        }
+    }
+
+    // duplicates, recognition errors, a switch timeout or a failed scroll
+    pub fn back_off(&mut self) {
+        if !self.adaptive {
+            return;
+        }
+        self.scroll_stop = clamp(self.scroll_stop * BACK_OFF, MIN_SCROLL_STOP, MAX_SCROLL_STOP);
+        self.switch_wait = clamp(self.switch_wait * BACK_OFF, MIN_SWITCH_WAIT, MAX_SWITCH_WAIT);
+        self.cooldown = COOLDOWN;
+        info!(
+            "timing backed off: scroll stop = {}ms, switch wait = {}ms",
+            self.scroll_stop(),
+            self.max_wait_switch_artifact()
+        );
+    }
+
+    // ms, 0 until the first switch was seen
+    pub fn latency(&self) -> u32 {
+        self.latency.unwrap_or(0.0).min(MAX_LATENCY).round() as u32
+    }
+
+    pub fn avg_switch_time(&self) -> f64 {
+        self.avg_switch_time
+    }
+
+    pub fn profile(&self) -> TimingProfile {
+        TimingProfile {
+            scroll_stop: self.scroll_stop(),
+            max_wait_switch_artifact: self.max_wait_switch_artifact(),
+            avg_switch_time: self.avg_switch_time,
+        }
+    }
+
+    pub fn save(&self, path: &str) {
+        if !self.adaptive {
+            return;
+        }
+        let profile = self.profile();
+        match profile.save(path) {
+            Ok(_) => info!("timing profile saved to {}: {:?}", path, profile),
+            Err(e) => warn!("{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile_path(name: &str) -> String {
+        let name = format!("yas_timing_{}_{}.json", name, std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let profile = TimingProfile {
+            scroll_stop: 200,
+            max_wait_switch_artifact: 1500,
+            avg_switch_time: 100.0,
+        };
+        profile.save(path.to_str().unwrap()).unwrap();
+        String::from(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn profile_fills_in_what_was_not_given() {
+        let path = profile_path("defaults");
+        let timing = Timing::adaptive(None, None, &path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(timing.scroll_stop(), 200);
+        assert_eq!(timing.max_wait_switch_artifact(), 1500);
+    }
+
+    #[test]
+    fn given_values_win_over_the_profile() {
+        let path = profile_path("given");
+        let timing = Timing::adaptive(Some(120), None, &path);
+        let timing_2 = Timing::adaptive(None, Some(900), &path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(timing.scroll_stop(), 120);
+        assert_eq!(timing.max_wait_switch_artifact(), 1500);
+        assert_eq!(timing_2.scroll_stop(), 200);
+        assert_eq!(timing_2.max_wait_switch_artifact(), 900);
+    }
+
+    #[test]
+    fn scroll_stop_follows_slower_scrolls() {
+        let mut timing = Timing::adaptive(Some(80), Some(800), "");
+        timing.on_scroll(300.0);
+        assert_eq!(timing.scroll_stop(), 450);
+        timing.on_scroll(300.0);
+        assert_eq!(timing.scroll_stop(), 450);
+        assert!(timing.max_scroll_stop() >= timing.scroll_stop());
+    }
+}
@@ -1,31 +1,39 @@
 use std::collections::HashSet;
 use std::convert::From;
 use std::fs;
-use std::io::stdin;
-use std::sync::mpsc;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::SystemTime;
 
 use clap::ArgMatches;
 use enigo::*;
 use log::{debug, error, info, warn};
 use rand::Rng;
 
+use crate::artifact::diff::{self, KnownArtifacts};
 use crate::artifact::internal_artifact::{
-    ArtifactSetName, ArtifactSlot, ArtifactStat, InternalArtifact,
+    assign_ids, ArtifactSetName, ArtifactSlot, ArtifactStat, InternalArtifact,
 };
-use crate::capture;
+use crate::capture::frame::{Frame, FrameGrabber};
 use crate::common::character_name::CHARACTER_NAMES;
-use crate::common::color::Color;
+use crate::common::color::{Color, SAME_COLOR_TOLERANCE, STREAM_COLOR_TOLERANCE};
+use crate::common::config_file::ConfigFile;
 use crate::common::{utils, PixelRect, PixelRectBound, RawCaptureImage, RawImage};
+use crate::expo::convert;
+use crate::dataset::{DatasetSample, DatasetWriter};
 use crate::inference::inference::CRNNModel;
 use crate::inference::pre_process::pre_process;
-use crate::info::info::ScanInfo;
-
-#[cfg(windows)]
-use crate::common::utils::{
-    find_window_cloud, find_window_local, get_client_rect, set_dpi_awareness,
-    show_window_and_set_foreground, sleep,
+use crate::info::info::{ScanInfo, DEFAULT_ASPECT_TOLERANCE};
+use crate::info::layout::Layouts;
+use crate::scanner::alignment;
+use crate::scanner::grid::GridProbe;
+use crate::scanner::paging::{self, PagingMode};
+use crate::scanner::scanner::{ScanError, ScanReport};
+use crate::scanner::switch::SwitchDetector;
+use crate::scanner::timing::{
+    Timing, DEFAULT_MAX_WAIT_SWITCH_ARTIFACT, DEFAULT_SCROLL_STOP, DEFAULT_TIMING_PROFILE,
 };
 
 pub struct YasScannerConfig {
@@ -33,60 +41,324 @@ pub struct YasScannerConfig {
     capture_only: bool,
     min_star: u32,
     min_level: u32,
-    max_wait_switch_artifact: u32,
-    scroll_stop: u32,
+    // None for the defaults, or the saved timing profile with adaptive timing
+    max_wait_switch_artifact: Option<u32>,
+    scroll_stop: Option<u32>,
     number: u32,
     verbose: bool,
     dump_mode: bool,
     cloud_wait_switch_artifact: u32,
-    // offset_x: i32,
-    // offset_y: i32,
+    dataset_dir: Option<String>,
+    offset_x: i32,
+    offset_y: i32,
+    pub(crate) auto_offset: bool,
+    layout: Option<String>,
+    layout_file: Option<String>,
+    aspect_tolerance: f64,
+    adaptive_timing: bool,
+    timing_profile: String,
+    paging: PagingMode,
+    pub(crate) cloud: bool,
+    // the previous export, for an incremental scan
+    since: Option<Vec<InternalArtifact>>,
 }
 
-impl YasScannerConfig {
-    pub fn from_match(matches: &ArgMatches) -> YasScannerConfig {
+impl Default for YasScannerConfig {
+    fn default() -> YasScannerConfig {
         YasScannerConfig {
-            max_row: matches
-                .value_of("max-row")
-                .unwrap_or("1000")
-                .parse::<u32>()
-                .unwrap(),
+            max_row: 1000,
+            capture_only: false,
+            min_star: 4,
+            min_level: 0,
+            max_wait_switch_artifact: None,
+            scroll_stop: None,
+            number: 0,
+            verbose: false,
+            dump_mode: false,
+            cloud_wait_switch_artifact: 300,
+            dataset_dir: None,
+            offset_x: 0,
+            offset_y: 0,
+            auto_offset: true,
+            layout: None,
+            layout_file: None,
+            aspect_tolerance: DEFAULT_ASPECT_TOLERANCE,
+            adaptive_timing: false,
+            timing_profile: String::from(DEFAULT_TIMING_PROFILE),
+            paging: PagingMode::Wheel,
+            cloud: false,
+            since: None,
+        }
+    }
+}
+
+// Builder style setters, for using yas as a library:
+//
+//   let config = YasScannerConfig::default().min_star(5).layout_file("layouts.json");
+impl YasScannerConfig {
+    pub fn max_row(mut self, v: u32) -> Self {
+        self.max_row = v;
+        self
+    }
+
+    pub fn capture_only(mut self, v: bool) -> Self {
+        self.capture_only = v;
+        self
+    }
+
+    pub fn min_star(mut self, v: u32) -> Self {
+        self.min_star = v;
+        self
+    }
+
+    pub fn min_level(mut self, v: u32) -> Self {
+        self.min_level = v;
+        self
+    }
+
+    pub fn max_wait_switch_artifact(mut self, ms: u32) -> Self {
+        self.max_wait_switch_artifact = Some(ms);
+        self
+    }
+
+    pub fn scroll_stop(mut self, ms: u32) -> Self {
+        self.scroll_stop = Some(ms);
+        self
+    }
+
+    // 0 detects the count from the backpack
+    pub fn number(mut self, v: u32) -> Self {
+        self.number = v;
+        self
+    }
+
+    pub fn verbose(mut self, v: bool) -> Self {
+        self.verbose = v;
+        self
+    }
+
+    pub fn dump_mode(mut self, v: bool) -> Self {
+        self.dump_mode = v;
+        self
+    }
+
+    pub fn cloud_wait_switch_artifact(mut self, ms: u32) -> Self {
+        self.cloud_wait_switch_artifact = ms;
+        self
+    }
+
+    pub fn dataset_dir(mut self, dir: &str) -> Self {
+        self.dataset_dir = Some(String::from(dir));
+        self
+    }
+
+    // a manual offset turns off auto offset detection
+    pub fn offset(mut self, x: i32, y: i32) -> Self {
+        self.offset_x = x;
+        self.offset_y = y;
+        self.auto_offset = false;
+        self
+    }
+
+    pub fn auto_offset(mut self, v: bool) -> Self {
+        self.auto_offset = v;
+        self
+    }
+
+    pub fn layout(mut self, name: &str) -> Self {
+        self.layout = Some(String::from(name));
+        self
+    }
+
+    pub fn layout_file(mut self, path: &str) -> Self {
+        self.layout_file = Some(String::from(path));
+        self
+    }
+
+    pub fn aspect_tolerance(mut self, v: f64) -> Self {
+        self.aspect_tolerance = v;
+        self
+    }
+
+    // treat the game window as a streamed client even if it is not recognized as one
+    pub fn cloud(mut self, v: bool) -> Self {
+        self.cloud = v;
+        self
+    }
+
+    pub fn paging(mut self, mode: PagingMode) -> Self {
+        self.paging = mode;
+        self
+    }
+
+    // Only scans what was obtained after `previous` was exported, the backpack has to be
+    // sorted by acquisition order. The result is merged into `previous`.
+    pub fn since(mut self, previous: Vec<InternalArtifact>) -> Self {
+        self.since = Some(previous);
+        self
+    }
+
+    // tune scroll stop and switch wait while scanning, starting from the profile at `path`
+    pub fn adaptive_timing(mut self, path: &str) -> Self {
+        self.adaptive_timing = true;
+        self.timing_profile = String::from(path);
+        self
+    }
+}
+
+// parses `--name`, if given
+fn parse_arg<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, String> {
+    match matches.value_of(name) {
+        Some(v) => match v.parse::<T>() {
+            Ok(v) => Ok(Some(v)),
+            Err(_) => Err(format!("参数--{}的值无效：{}", name, v)),
+        },
+        None => Ok(None),
+    }
+}
+
+// `--name` or `--no-name` on the command line, then the config file
+fn flag(matches: &ArgMatches, name: &str, file: Option<bool>, default: bool) -> bool {
+    if matches.is_present(name) {
+        true
+    } else if matches.is_present(&format!("no-{}", name)) {
+        false
+    } else {
+        file.unwrap_or(default)
+    }
+}
+
+impl YasScannerConfig {
+    // Settings from `file`, overridden by the command line.
+    pub fn from_match(matches: &ArgMatches, file: &ConfigFile) -> Result<YasScannerConfig, String> {
+        let default = YasScannerConfig::default();
+        let offset_x = parse_arg::<i32>(matches, "offset-x")?;
+        let offset_y = parse_arg::<i32>(matches, "offset-y")?;
+        // a manual offset, from either source, turns off auto offset detection
+        let manual_offset = offset_x.is_some()
+            || offset_y.is_some()
+            || file.offset_x.is_some()
+            || file.offset_y.is_some();
+
+        let config = YasScannerConfig {
+            max_row: parse_arg(matches, "max-row")?
+                .or(file.max_row)
+                .unwrap_or(default.max_row),
             capture_only: matches.is_present("capture-only"),
             dump_mode: matches.is_present("dump"),
-            min_star: matches
-                .value_of("min-star")
-                .unwrap_or("4")
-                .parse::<u32>()
-                .unwrap(),
-            min_level: matches
-                .value_of("min-level")
-                .unwrap_or("0")
-                .parse::<u32>()
-                .unwrap(),
-            max_wait_switch_artifact: matches
-                .value_of("max-wait-switch-artifact")
-                .unwrap_or("800")
-                .parse::<u32>()
-                .unwrap(),
-            scroll_stop: matches
-                .value_of("scroll-stop")
-                .unwrap_or("80")
-                .parse::<u32>()
-                .unwrap(),
-            number: matches
-                .value_of("number")
-                .unwrap_or("0")
-                .parse::<u32>()
-                .unwrap(),
-            verbose: matches.is_present("verbose"),
-            cloud_wait_switch_artifact: matches
-                .value_of("cloud-wait-switch-artifact")
-                .unwrap_or("300")
-                .parse::<u32>()
-                .unwrap(),
-            // offset_x: matches.value_of("offset-x").unwrap_or("0").parse::<i32>().unwrap(),
-            // offset_y: matches.value_of("offset-y").unwrap_or("0").parse::<i32>().unwrap(),
+            min_star: parse_arg(matches, "min-star")?
+                .or(file.min_star)
+                .unwrap_or(default.min_star),
+            min_level: parse_arg(matches, "min-level")?
+                .or(file.min_level)
+                .unwrap_or(default.min_level),
+            max_wait_switch_artifact: parse_arg(matches, "max-wait-switch-artifact")?
+                .or(file.max_wait_switch_artifact),
+            scroll_stop: parse_arg(matches, "scroll-stop")?.or(file.scroll_stop),
+            number: parse_arg(matches, "number")?
+                .or(file.number)
+                .unwrap_or(default.number),
+            verbose: flag(matches, "verbose", file.verbose, default.verbose),
+            cloud_wait_switch_artifact: parse_arg(matches, "cloud-wait-switch-artifact")?
+                .or(file.cloud_wait_switch_artifact)
+                .unwrap_or(default.cloud_wait_switch_artifact),
+            dataset_dir: matches
+                .value_of("dataset")
+                .map(String::from)
+                .or_else(|| file.dataset.clone()),
+            offset_x: offset_x.or(file.offset_x).unwrap_or(default.offset_x),
+            offset_y: offset_y.or(file.offset_y).unwrap_or(default.offset_y),
+            auto_offset: !matches.is_present("no-auto-offset")
+                && !manual_offset
+                && file.auto_offset.unwrap_or(default.auto_offset),
+            layout: matches
+                .value_of("layout")
+                .map(String::from)
+                .or_else(|| file.layout.clone()),
+            layout_file: matches
+                .value_of("layout-file")
+                .map(String::from)
+                .or_else(|| file.layout_file.clone()),
+            aspect_tolerance: parse_arg(matches, "aspect-tolerance")?
+                .or(file.aspect_tolerance)
+                .unwrap_or(default.aspect_tolerance),
+            adaptive_timing: flag(
+                matches,
+                "adaptive-timing",
+                file.adaptive_timing,
+                default.adaptive_timing,
+            ),
+            timing_profile: matches
+                .value_of("timing-profile")
+                .map(String::from)
+                .or_else(|| file.timing_profile.clone())
+                .unwrap_or(default.timing_profile),
+            paging: match matches.value_of("paging").or(file.paging.as_deref()) {
+                Some(v) => v.parse::<PagingMode>()?,
+                None => default.paging,
+            },
+            cloud: flag(matches, "cloud", file.cloud, default.cloud),
+            since: match matches.value_of("since") {
+                Some(path) => match convert::load(None, Path::new(path)) {
+                    Ok(v) => Some(v),
+                    Err(e) => return Err(format!("读取上次导出的结果失败：{}", e)),
+                },
+                None => None,
+            },
+        };
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.min_star < 1 || self.min_star > 5 {
+            return Err(format!("最小星级应在1到5之间：{}", self.min_star));
         }
+        if self.max_row == 0 {
+            return Err(String::from("最大扫描行数不能为0"));
+        }
+        if !(self.aspect_tolerance >= 0.0) {
+            return Err(format!("窗口比例误差不能为负数：{}", self.aspect_tolerance));
+        }
+
+        Ok(())
+    }
+
+    // the layout for a window of size `rect`, honoring `--layout` and the layout file
+    pub fn scan_info(&self, rect: &PixelRect) -> Result<ScanInfo, String> {
+        let mut layouts = Layouts::builtin();
+        let layout_file = match self.layout_file {
+            Some(ref v) => Some(v.as_str()),
+            None if Path::new("layouts.json").exists() => Some("layouts.json"),
+            None => None,
+        };
+        if let Some(path) = layout_file {
+            if let Err(e) = layouts.load(path) {
+                return Err(format!("读取窗口布局文件失败：{}", e));
+            }
+        }
+
+        let mut info = match self.layout {
+            Some(ref name) => match layouts.get(name) {
+                Some(layout) => {
+                    info!("using layout {}", layout.name);
+                    ScanInfo::from_window_info(&layout.window_info, rect)
+                }
+                None => {
+                    return Err(format!(
+                        "未找到窗口布局 {}，可用的布局：{}",
+                        name,
+                        layouts.names().join(", ")
+                    ))
+                }
+            },
+            None => ScanInfo::from_rect_with_layouts(rect, &layouts, self.aspect_tolerance)?,
+        };
+        info.left += self.offset_x;
+        info.top += self.offset_y;
+
+        Ok(info)
     }
 }
 
@@ -100,16 +372,24 @@ pub struct YasScanner {
     row: u32,
     col: u32,
 
-    pool: f64,
+    switch_detector: SwitchDetector,
+    // the same, on the grid, to time scrolls
+    scroll_detector: SwitchDetector,
+    grabber: FrameGrabber,
 
     initial_color: Color,
 
     // for scrolls
     scrolled_rows: u32,
+    // measured grid offset (px) after the last scroll
+    row_offset: i32,
+    // the first fully visible row, counted from the top of the backpack
+    visible_row: u32,
     avg_scroll_one_row: f64,
 
-    avg_switch_time: f64,
-    scanned_count: u32,
+    timing: Timing,
+    // dups and recognition errors, counted by the recognition thread
+    issue_count: Arc<AtomicU32>,
 
     is_cloud: bool,
 }
@@ -124,16 +404,16 @@ enum ScrollResult {
 
 #[derive(Debug)]
 pub struct YasScanResult {
-    name: String,
-    main_stat_name: String,
-    main_stat_value: String,
-    sub_stat_1: String,
-    sub_stat_2: String,
-    sub_stat_3: String,
-    sub_stat_4: String,
-    level: String,
-    equip: String,
-    star: u32,
+    pub name: String,
+    pub main_stat_name: String,
+    pub main_stat_value: String,
+    pub sub_stat_1: String,
+    pub sub_stat_2: String,
+    pub sub_stat_3: String,
+    pub sub_stat_4: String,
+    pub level: String,
+    pub equip: String,
+    pub star: u32,
 }
 
 impl YasScanResult {
@@ -185,28 +465,66 @@ impl YasScanResult {
             sub_stat_3: sub3,
             sub_stat_4: sub4,
             equip,
+            id: None,
         };
         Some(art)
     }
 }
 
-fn calc_pool(row: &Vec<u8>) -> f64 {
-    let len = row.len() / 4;
-    let mut pool: f64 = 0.0;
+fn save_capture(im: Option<RawImage>, path: &str) -> Result<(), ScanError> {
+    match im {
+        Some(im) => im
+            .to_gray_image()
+            .save(path)
+            .map_err(|e| ScanError::Io(format!("无法保存{}：{}", path, e))),
+        None => Ok(()),
+    }
+}
+
+// The count in the backpack header, "圣遗物 1234/1500". Text that is not the header gives None,
+// a header too short to hold a count is an error.
+fn parse_art_count(s: &str) -> Result<Option<u32>, String> {
+    if !s.starts_with("圣遗物") {
+        return Ok(None);
+    }
+    let chars = s.chars().collect::<Vec<char>>();
+    if chars.len() < 10 {
+        return Err(String::from(s));
+    }
+    let count_str = chars[4..chars.len() - 5].iter().collect::<String>();
+    Ok(count_str.parse::<u32>().ok())
+}
 
-    for i in 0..len {
-        pool += row[i * 4] as f64;
+fn window_rect(info: &ScanInfo) -> PixelRect {
+    PixelRect {
+        left: info.left,
+        top: info.top,
+        width: info.width as i32,
+        height: info.height as i32,
     }
-    // pool /= len as f64;
-    pool
 }
 
 impl YasScanner {
-    pub fn new(info: ScanInfo, config: YasScannerConfig, is_cloud: bool) -> YasScanner {
+    pub fn new(info: ScanInfo, config: YasScannerConfig, is_cloud: bool) -> Result<YasScanner, String> {
         let row = info.art_row;
         let col = info.art_col;
+        let grabber = FrameGrabber::new(window_rect(&info))?;
+        let timing = if config.adaptive_timing {
+            Timing::adaptive(
+                config.scroll_stop,
+                config.max_wait_switch_artifact,
+                &config.timing_profile,
+            )
+        } else {
+            Timing::fixed(
+                config.scroll_stop.unwrap_or(DEFAULT_SCROLL_STOP),
+                config
+                    .max_wait_switch_artifact
+                    .unwrap_or(DEFAULT_MAX_WAIT_SWITCH_ARTIFACT),
+            )
+        };
 
-        YasScanner {
+        Ok(YasScanner {
             model: CRNNModel::new(
                 String::from("model_training.onnx"),
                 String::from("index_2_word.json"),
@@ -218,16 +536,28 @@ impl YasScanner {
             row,
             col,
 
-            pool: -1.0,
+            grabber,
+            switch_detector: if is_cloud {
+                SwitchDetector::cloud()
+            } else {
+                SwitchDetector::local()
+            },
+            scroll_detector: if is_cloud {
+                SwitchDetector::cloud()
+            } else {
+                SwitchDetector::local()
+            },
             initial_color: Color::new(),
             scrolled_rows: 0,
+            row_offset: 0,
+            visible_row: 0,
             avg_scroll_one_row: 0.0,
 
-            avg_switch_time: 0.0,
-            scanned_count: 0,
+            timing,
+            issue_count: Arc::new(AtomicU32::new(0)),
 
             is_cloud,
-        }
+        })
     }
 }
 
@@ -239,7 +569,8 @@ impl YasScanner {
                 as i32;
         let top = info.top
             + (info.top_margin + (info.art_height + info.art_gap_y) * row + info.art_height / 4)
-                as i32;
+                as i32
+            + self.row_offset;
         self.enigo.mouse_move_to(left as i32, top as i32);
     }
 
@@ -263,7 +594,7 @@ impl YasScanner {
             #[cfg(target_os = "linux")]
             self.enigo.mouse_scroll_y(-1);
 
-            utils::sleep(self.config.scroll_stop);
+            utils::sleep(self.timing.scroll_stop());
             color = capture::get_color(
                 info.level_position.left as u32,
                 info.level_position.bottom as u32,
@@ -273,50 +604,117 @@ impl YasScanner {
     }
     */
 
-    fn sample_initial_color(&mut self) {
-        self.initial_color = self.get_flag_color();
+    fn sample_initial_color(&mut self) -> Result<(), String> {
+        self.initial_color = self.get_flag_color()?;
+        Ok(())
     }
 
-    fn get_flag_color(&self) -> Color {
+    fn get_flag_color(&self) -> Result<Color, String> {
         let flag_x = self.info.flag_x as i32 + self.info.left;
         let flag_y = self.info.flag_y as i32 + self.info.top;
-        let color = capture::get_color(flag_x as u32, flag_y as u32);
+        let radius = if self.is_cloud { 2 } else { 0 };
+        self.grabber.get_color(flag_x, flag_y, radius)
+    }
+
+    fn is_flag_color(&self, color: &Color) -> bool {
+        let tolerance = if self.is_cloud {
+            STREAM_COLOR_TOLERANCE
+        } else {
+            SAME_COLOR_TOLERANCE
+        };
+        color.is_same_within(&self.initial_color, tolerance)
+    }
+
+    // a streamed client shows the result of a scroll only after the round trip
+    fn scroll_wait(&self) -> u32 {
+        self.with_latency(self.timing.scroll_stop())
+    }
+
+    fn with_latency(&self, ms: u32) -> u32 {
+        if self.is_cloud {
+            ms + self.timing.latency()
+        } else {
+            ms
+        }
+    }
+
+    // the visible cards
+    fn grid_rect(&self) -> PixelRect {
+        let info = &self.info;
+        PixelRect {
+            left: info.left + info.left_margin as i32,
+            top: info.top + info.top_margin as i32,
+            width: ((info.art_width + info.art_gap_x) * info.art_col) as i32,
+            height: (((info.art_height + info.art_gap_y) * info.art_row) as i32)
+                .min(info.height as i32 - info.top_margin as i32),
+        }
+    }
+
+    // Waits out a scroll. With adaptive timing the wait ends once the grid stops moving, and
+    // how long that took sets the next scroll stop.
+    fn wait_scroll(&mut self) -> Result<(), String> {
+        if !self.timing.is_adaptive() {
+            utils::sleep(self.scroll_wait());
+            return Ok(());
+        }
+        let max_wait = self.with_latency(self.timing.max_scroll_stop());
+        let rect = self.grid_rect();
+        if let Some(ms) = self.scroll_detector.wait(&self.grabber, &rect, &rect, max_wait)? {
+            self.timing.on_scroll(ms as f64);
+        }
+        Ok(())
+    }
+
+    // Shifts `info` onto the real window content using the panel border, then checks that the
+    // "圣遗物" count label is readable at the corrected position.
+    pub fn auto_align(&mut self) -> Result<(i32, i32), String> {
+        let (dx, dy) = alignment::detect_offset(&self.info, &self.grabber)?;
+        self.info.left += dx;
+        self.info.top += dy;
+        self.grabber = FrameGrabber::new(window_rect(&self.info))?;
+
+        let count = self.info.art_count_position.capture_relative(&self.info);
+        let s = match count {
+            Ok(im) => self.model.inference_string(&im),
+            Err(_) => String::new(),
+        };
+        if !s.starts_with("圣遗物") {
+            self.info.left -= dx;
+            self.info.top -= dy;
+            self.grabber = FrameGrabber::new(window_rect(&self.info))?;
+            return Err(format!("偏移校正后仍无法识别圣遗物数量（识别结果：{}）", s));
+        }
 
-        color
+        info!("auto offset: x = {}, y = {}", dx, dy);
+        Ok((dx, dy))
     }
 
-    fn get_art_count(&mut self) -> Result<u32, String> {
+    // None if the count label cannot be read
+    fn get_art_count(&mut self) -> Result<Option<u32>, ScanError> {
         let count = self.config.number;
         if let 0 = count {
             let info = &self.info;
-            let raw_after_pp = self.info.art_count_position.capture_relative(info).unwrap();
+            let raw_after_pp = self
+                .info
+                .art_count_position
+                .capture_relative(info)
+                .map_err(ScanError::Capture)?;
             // raw_after_pp.to_gray_image().save("count.png");
             let s = self.model.inference_string(&raw_after_pp);
             info!("raw count string: {}", s);
-            if s.starts_with("圣遗物") {
-                let chars = s.chars().collect::<Vec<char>>();
-                let count_str = (&chars[4..chars.len() - 5]).iter().collect::<String>();
-                let count = match count_str.parse::<u32>() {
-                    Ok(v) => v,
-                    Err(_) => {
-                        return Err(String::from("无法识别圣遗物数量"));
-                    }
-                };
-                return Ok(count);
-            }
-            Err(String::from("无法识别圣遗物数量"))
+            parse_art_count(&s).map_err(ScanError::Count)
         } else {
-            return Ok(count);
+            return Ok(Some(count));
         }
     }
 
-    fn scroll_one_row(&mut self) -> ScrollResult {
+    fn scroll_one_row(&mut self) -> Result<ScrollResult, String> {
         let mut state = 0;
         let mut count = 0;
         let max_scroll = 20;
         while count < max_scroll {
             if utils::is_rmb_down() {
-                return ScrollResult::Interrupt;
+                return Ok(ScrollResult::Interrupt);
             }
 
             #[cfg(windows)]
@@ -324,26 +722,114 @@ impl YasScanner {
             #[cfg(target_os = "linux")]
             self.enigo.mouse_scroll_y(1);
 
-            utils::sleep(self.config.scroll_stop);
+            self.wait_scroll()?;
             count += 1;
-            let color: Color = self.get_flag_color();
-            // println!("{:?}", color);
-            if state == 0 && !color.is_same(&self.initial_color) {
-                state = 1;
-            } else if state == 1 && self.initial_color.is_same(&color) {
+            let moved_one_row = match self.grid_offset() {
+                // the card tops move up out of the tolerance, wrap around to below, and come
+                // back into it when exactly one row has passed
+                Some(offset) => {
+                    let tolerance = self.grid_tolerance();
+                    if state != 2 && offset > tolerance {
+                        state = 2;
+                    } else if state == 0 && offset < -tolerance {
+                        state = 1;
+                    }
+                    state == 2 && offset <= tolerance
+                }
+                None => {
+                    let color: Color = self.get_flag_color()?;
+                    if state == 0 && !self.is_flag_color(&color) {
+                        state = 1;
+                    }
+                    state == 1 && self.is_flag_color(&color)
+                }
+            };
+            if moved_one_row {
                 self.avg_scroll_one_row = (self.avg_scroll_one_row * self.scrolled_rows as f64
                     + count as f64)
                     / (self.scrolled_rows as f64 + 1.0);
                 info!("avg scroll/row: {}", self.avg_scroll_one_row);
                 self.scrolled_rows += 1;
-                return ScrollResult::Success;
+                self.align_row()?;
+                return Ok(ScrollResult::Success);
+            }
+        }
+
+        Ok(ScrollResult::TLE)
+    }
+
+    // Scrolls down up to `count` rows, keeping the last visible row in view so the move can be
+    // measured, and updates `visible_row` with how far the grid really went.
+    fn scroll_rows(&mut self, count: u32) -> Result<ScrollResult, String> {
+        let count = count.min(self.row.saturating_sub(1)).max(1);
+        let probe = GridProbe::new(&self.info);
+        let before = probe.profile(&self.grabber);
+
+        let result = match self.config.paging {
+            PagingMode::Wheel => self.scroll_rows_wheel(count)?,
+            PagingMode::Drag => self.drag_rows(count)?,
+        };
+        if let ScrollResult::Success | ScrollResult::Skip = result {
+            let after = probe.profile(&self.grabber);
+            let shift = match (before, after) {
+                (Some(b), Some(a)) => probe.shift(&b, &a),
+                _ => None,
+            };
+            match shift {
+                Some(px) => {
+                    let rows = (px as f64 / probe.pitch() as f64).round() as i32;
+                    if rows != count as i32 {
+                        warn!("scrolled {} rows instead of {}", rows, count);
+                    }
+                    self.visible_row = (self.visible_row as i32 + rows).max(0) as u32;
+                }
+                None => {
+                    warn!("cannot measure the scroll, assuming {} rows", count);
+                    self.visible_row += count;
+                }
             }
         }
 
-        ScrollResult::TLE
+        Ok(result)
     }
 
-    fn scroll_rows(&mut self, count: u32) -> ScrollResult {
+    // a point on the gap between the first two columns, so pressing there selects nothing
+    fn drag_x(&self) -> i32 {
+        let info = &self.info;
+        info.left + (info.left_margin + info.art_width + info.art_gap_x / 2) as i32
+    }
+
+    fn drag_rows(&mut self, count: u32) -> Result<ScrollResult, String> {
+        if utils::is_rmb_down() {
+            return Ok(ScrollResult::Interrupt);
+        }
+        let info = &self.info;
+        let pitch = (info.art_height + info.art_gap_y) as i32;
+        // from the middle of the last visible row, so there is room to drag up
+        let from_y = info.top
+            + info.top_margin as i32
+            + pitch * (self.row as i32 - 1)
+            + info.art_height as i32 / 2;
+        // a drag has to stay on the grid, so it goes up to the first visible row at most
+        let max = pitch * (self.row as i32 - 1);
+        let x = self.drag_x();
+
+        let distances = paging::split_drag(pitch * count as i32, max);
+        for (i, distance) in distances.into_iter().enumerate() {
+            if i > 0 && utils::is_rmb_down() {
+                return Ok(ScrollResult::Interrupt);
+            }
+            paging::drag(&mut self.enigo, x, from_y, from_y - distance);
+            self.wait_scroll()?;
+        }
+        if !self.align_row()? {
+            return Ok(ScrollResult::TLE);
+        }
+
+        Ok(ScrollResult::Success)
+    }
+
+    fn scroll_rows_wheel(&mut self, count: u32) -> Result<ScrollResult, String> {
         if self.scrolled_rows >= 5 {
             let scroll = ((self.avg_scroll_one_row * count as f64 - 3.0).round() as u32).max(0);
             for _ in 0..scroll {
@@ -353,97 +839,131 @@ impl YasScanner {
                 self.enigo.mouse_scroll_y(1);
             }
             utils::sleep(400);
-            self.align_row();
-            return ScrollResult::Skip;
+            self.align_row()?;
+            return Ok(ScrollResult::Skip);
         }
 
         for _ in 0..count {
-            match self.scroll_one_row() {
-                ScrollResult::TLE => return ScrollResult::TLE,
-                ScrollResult::Interrupt => return ScrollResult::Interrupt,
+            match self.scroll_one_row()? {
+                ScrollResult::TLE => return Ok(ScrollResult::TLE),
+                ScrollResult::Interrupt => return Ok(ScrollResult::Interrupt),
                 _ => (),
             }
         }
 
-        ScrollResult::Success
+        Ok(ScrollResult::Success)
+    }
+
+    fn grid_offset(&self) -> Option<i32> {
+        GridProbe::new(&self.info).measure(&self.grabber)
+    }
+
+    fn grid_tolerance(&self) -> i32 {
+        GridProbe::new(&self.info).tolerance()
     }
 
-    fn align_row(&mut self) -> bool {
+    // Scrolls until the grid is where the layout puts it, and remembers what is left of the
+    // offset so clicks still land on the cards. Falls back to the flag pixel when the card
+    // borders cannot be found.
+    fn align_row(&mut self) -> Result<bool, String> {
         let mut count = 0;
         while count < 10 {
-            let color = self.get_flag_color();
-            if color.is_same(&self.initial_color) {
-                return true;
-            }
+            // positive when the cards sit too low
+            let offset = match self.grid_offset() {
+                Some(v) if v.abs() <= self.grid_tolerance() => {
+                    self.row_offset = v;
+                    return Ok(true);
+                }
+                Some(v) => v,
+                None => {
+                    self.row_offset = 0;
+                    if self.is_flag_color(&self.get_flag_color()?) {
+                        return Ok(true);
+                    }
+                    1
+                }
+            };
 
-            #[cfg(windows)]
-            self.enigo.mouse_scroll_y(-1);
-            #[cfg(target_os = "linux")]
-            self.enigo.mouse_scroll_y(1);
+            match self.config.paging {
+                // scrolling down moves the cards up
+                PagingMode::Wheel => {
+                    #[cfg(windows)]
+                    self.enigo.mouse_scroll_y(if offset > 0 { -1 } else { 1 });
+                    #[cfg(target_os = "linux")]
+                    self.enigo.mouse_scroll_y(if offset > 0 { 1 } else { -1 });
+                }
+                PagingMode::Drag => {
+                    let x = self.drag_x();
+                    let y = self.info.top + (self.info.height / 2) as i32;
+                    paging::drag(&mut self.enigo, x, y, y - offset);
+                }
+            }
 
-            utils::sleep(self.config.scroll_stop);
+            utils::sleep(self.scroll_wait());
             count += 1;
         }
 
-        false
+        self.row_offset = self.grid_offset().unwrap_or(0);
+        warn!("cannot align row, offset = {}px", self.row_offset);
+        Ok(false)
     }
 
-    fn wait_until_switched(&mut self) -> bool {
-        if self.is_cloud {
-            utils::sleep(self.config.cloud_wait_switch_artifact);
-            return true;
-        }
-        let now = SystemTime::now();
-
-        let mut consecutive_time = 0;
-        let mut diff_flag = false;
-        while now.elapsed().unwrap().as_millis() < self.config.max_wait_switch_artifact as u128 {
-            // let pool_start = SystemTime::now();
-            let rect = PixelRect {
-                left: self.info.left as i32 + self.info.pool_position.left,
-                top: self.info.top as i32 + self.info.pool_position.top,
-                width: self.info.pool_position.right - self.info.pool_position.left,
-                height: self.info.pool_position.bottom - self.info.pool_position.top,
-            };
-            let im = capture::capture_absolute(&rect).unwrap();
-            let pool = calc_pool(&im);
-            // info!("pool: {}", pool);
-            // println!("pool time: {}ms", pool_start.elapsed().unwrap().as_millis());
-
-            if (pool - self.pool).abs() > 0.000001 {
-                // info!("pool: {}", pool);
-                // let raw = RawCaptureImage {
-                //     data: im,
-                //     w: rect.width as u32,
-                //     h: rect.height as u32,
-                // };
-                // let raw = raw.to_raw_image();
-                // println!("{:?}", &raw.data[..10]);
-                // raw.save(&format!("captures/{}.png", rand::thread_rng().gen::<u32>()));
-
-                self.pool = pool;
-                diff_flag = true;
-                consecutive_time = 0;
-                // info!("avg switch time: {}ms", self.avg_switch_time);
-            } else {
-                if diff_flag {
-                    consecutive_time += 1;
-                    if consecutive_time == 1 {
-                        self.avg_switch_time = (self.avg_switch_time * self.scanned_count as f64
-                            + now.elapsed().unwrap().as_millis() as f64)
-                            / (self.scanned_count as f64 + 1.0);
-                        self.scanned_count += 1;
-                        return true;
-                    }
-                }
+    // the title and stat lines of the detail panel
+    fn switch_rect(&self) -> PixelRect {
+        let info = &self.info;
+        PixelRect {
+            left: info.left + info.panel_position.left,
+            top: info.top + info.title_position.top,
+            width: info.panel_position.right - info.panel_position.left,
+            height: info.sub_stat4_position.bottom - info.title_position.top,
+        }
+    }
+
+    fn wait_until_switched(&mut self) -> Result<bool, String> {
+        let rect = self.switch_rect();
+        // the cloud stream lags behind the click
+        let max_wait = if self.is_cloud {
+            let lag = self.config.cloud_wait_switch_artifact.max(self.timing.latency() * 2);
+            self.timing.max_wait_switch_artifact() + lag
+        } else {
+            self.timing.max_wait_switch_artifact()
+        };
+
+        let area = self.panel_area();
+        match self.switch_detector.wait(&self.grabber, &area, &rect, max_wait)? {
+            Some(ms) => {
+                self.timing.on_switch(ms as f64);
+                Ok(true)
             }
+            None => Ok(false),
         }
+    }
 
-        false
+    // what is read after a switch: the panel, its title and stat lines, and the star pixel
+    fn panel_area(&self) -> PixelRect {
+        let info = &self.info;
+        let switch = self.switch_rect();
+        let left = (info.left + info.panel_position.left)
+            .min(switch.left)
+            .min(info.left + info.star_x as i32);
+        let top = (info.top + info.panel_position.top)
+            .min(switch.top)
+            .min(info.top + info.star_y as i32);
+        let right = (info.left + info.panel_position.right)
+            .max(switch.left + switch.width)
+            .max(info.left + info.star_x as i32 + 1);
+        let bottom = (info.top + info.panel_position.bottom)
+            .max(switch.top + switch.height)
+            .max(info.top + info.star_y as i32 + 1);
+        PixelRect {
+            left,
+            top,
+            width: right - left,
+            height: bottom - top,
+        }
     }
 
-    fn capture_panel(&mut self) -> Result<RawCaptureImage, String> {
-        let now = SystemTime::now();
+    fn capture_panel(&self, frame: &Frame) -> RawCaptureImage {
         let w = self.info.panel_position.right - self.info.panel_position.left;
         let h = self.info.panel_position.bottom - self.info.panel_position.top;
         let rect: PixelRect = PixelRect {
@@ -452,19 +972,13 @@ impl YasScanner {
             width: w,
             height: h,
         };
-        let u8_arr = capture::capture_absolute(&rect)?;
-        // info!("capture time: {}ms", now.elapsed().unwrap().as_millis());
-        Ok(RawCaptureImage {
-            data: u8_arr,
-            w: w as u32,
-            h: h as u32,
-        })
+        frame.crop_capture(&rect)
     }
 
-    fn get_star(&self) -> u32 {
-        let color = capture::get_color(
-            (self.info.star_x as i32 + self.info.left) as u32,
-            (self.info.star_y as i32 + self.info.top) as u32,
+    fn get_star(&self, frame: &Frame) -> u32 {
+        let color = frame.get_color(
+            self.info.star_x as i32 + self.info.left,
+            self.info.star_y as i32 + self.info.top,
         );
 
         let color_1 = Color::from(113, 119, 139);
@@ -491,12 +1005,17 @@ impl YasScanner {
         star
     }
 
-    fn start_capture_only(&mut self) {
-        fs::create_dir("captures");
+    fn start_capture_only(&mut self) -> Result<(), ScanError> {
+        fs::create_dir_all("captures")
+            .map_err(|e| ScanError::Io(format!("无法创建captures目录：{}", e)))?;
         let info = &self.info.clone();
 
-        let count = self.info.art_count_position.capture_relative(info).unwrap();
-        count.to_gray_image().save("captures/count.png");
+        let count = self
+            .info
+            .art_count_position
+            .capture_relative(info)
+            .map_err(ScanError::Capture)?;
+        save_capture(Some(count), "captures/count.png")?;
 
         let convert_rect = |rect: &PixelRectBound| PixelRect {
             left: rect.left - info.panel_position.left,
@@ -505,81 +1024,37 @@ impl YasScanner {
             height: rect.bottom - rect.top,
         };
 
-        let panel = self.capture_panel().unwrap();
-        let im_title = pre_process(panel.crop_to_raw_img(&convert_rect(&info.title_position)));
-        if let Some(im) = im_title {
-            im.to_gray_image().save("captures/title.png").expect("Err");
-        }
-
-        let im_main_stat_name =
-            pre_process(panel.crop_to_raw_img(&convert_rect(&info.main_stat_name_position)));
-        if let Some(im) = im_main_stat_name {
-            im.to_gray_image()
-                .save("captures/main_stat_name.png")
-                .expect("Err");
-        }
-
-        let im_main_stat_value =
-            pre_process(panel.crop_to_raw_img(&convert_rect(&info.main_stat_value_position)));
-        if let Some(im) = im_main_stat_value {
-            im.to_gray_image()
-                .save("captures/main_stat_value.png")
-                .expect("Err");
-        }
-
-        let im_sub_stat_1 =
-            pre_process(panel.crop_to_raw_img(&convert_rect(&info.sub_stat1_position)));
-        if let Some(im) = im_sub_stat_1 {
-            im.to_gray_image()
-                .save("captures/sub_stat_1.png")
-                .expect("Err");
-        }
-
-        let im_sub_stat_2 =
-            pre_process(panel.crop_to_raw_img(&convert_rect(&info.sub_stat2_position)));
-        if let Some(im) = im_sub_stat_2 {
-            im.to_gray_image()
-                .save("captures/sub_stat_2.png")
-                .expect("Err");
+        let frame = self.grabber.grab().map_err(ScanError::Capture)?;
+        let panel = self.capture_panel(&frame);
+        let fields = [
+            (&info.title_position, "title"),
+            (&info.main_stat_name_position, "main_stat_name"),
+            (&info.main_stat_value_position, "main_stat_value"),
+            (&info.sub_stat1_position, "sub_stat_1"),
+            (&info.sub_stat2_position, "sub_stat_2"),
+            (&info.sub_stat3_position, "sub_stat_3"),
+            (&info.sub_stat4_position, "sub_stat_4"),
+            (&info.level_position, "level"),
+            (&info.equip_position, "equip"),
+        ];
+        for (pos, name) in fields.iter() {
+            let im = pre_process(panel.crop_to_raw_img(&convert_rect(pos)));
+            save_capture(im, &format!("captures/{}.png", name))?;
         }
 
-        let im_sub_stat_3 =
-            pre_process(panel.crop_to_raw_img(&convert_rect(&info.sub_stat3_position)));
-        if let Some(im) = im_sub_stat_3 {
-            im.to_gray_image()
-                .save("captures/sub_stat_3.png")
-                .expect("Err");
-        }
-
-        let im_sub_stat_4 =
-            pre_process(panel.crop_to_raw_img(&convert_rect(&info.sub_stat4_position)));
-        if let Some(im) = im_sub_stat_4 {
-            im.to_gray_image()
-                .save("captures/sub_stat_4.png")
-                .expect("Err");
-        }
-
-        let im_level = pre_process(panel.crop_to_raw_img(&convert_rect(&info.level_position)));
-        if let Some(im) = im_level {
-            im.to_gray_image().save("captures/level.png").expect("Err");
-        }
-
-        let im_equip = pre_process(panel.crop_to_raw_img(&convert_rect(&info.equip_position)));
-        if let Some(im) = im_equip {
-            im.to_gray_image().save("captures/equip.png").expect("Err");
-        }
+        Ok(())
     }
 
-    pub fn start(&mut self) -> Vec<InternalArtifact> {
+    pub fn start(&mut self) -> Result<ScanReport, ScanError> {
         //self.panel_down();
         if self.config.capture_only {
-            self.start_capture_only();
-            return Vec::new();
+            self.start_capture_only()?;
+            return Ok(ScanReport::default());
         }
 
-        let mut count = match self.get_art_count() {
-            Ok(v) => v,
-            Err(_) => 1500,
+        let mut count = match self.get_art_count()? {
+            Some(v) => v,
+            None => 1500,
         };
 
         let total_row = (count + self.col - 1) / self.col;
@@ -600,12 +1075,24 @@ impl YasScanner {
         info!("total row: {}", total_row);
         info!("last column: {}", last_row_col);
 
+        if self.config.dump_mode {
+            fs::create_dir_all("dumps")
+                .map_err(|e| ScanError::Io(format!("无法创建dumps目录：{}", e)))?;
+        }
+
         let (tx, rx) = mpsc::channel::<Option<(RawCaptureImage, u32)>>();
         let info_2 = self.info.clone();
         // v bvvmnvbm
         let is_verbose = self.config.verbose;
         let is_dump_mode = self.config.dump_mode;
         let min_level = self.config.min_level;
+        let dataset_dir = self.config.dataset_dir.clone();
+        let issue_count = self.issue_count.clone();
+        let known = self.config.since.as_ref().map(|v| KnownArtifacts::new(v));
+        let is_incremental = known.is_some();
+        // set by the recognition thread once it reaches what the previous export has
+        let reached_known = Arc::new(AtomicBool::new(false));
+        let reached_known_2 = reached_known.clone();
         let handle = thread::spawn(move || {
             let mut results: Vec<InternalArtifact> = Vec::new();
             let mut model = CRNNModel::new(
@@ -616,12 +1103,20 @@ impl YasScanner {
             let mut dup_count = 0;
             let mut hash = HashSet::new();
             let mut consecutive_dup_count = 0;
+            let mut consecutive_known_count = 0;
             let info = info_2;
 
             let mut cnt = 0;
-            if is_dump_mode {
-                fs::create_dir("dumps").expect("Err");
-            }
+            let mut dataset = match dataset_dir {
+                Some(ref dir) => match DatasetWriter::new(dir) {
+                    Ok(v) => Some(v),
+                    Err(e) => {
+                        error!("{}", e);
+                        None
+                    }
+                },
+                None => None,
+            };
 
             let convert_rect = |rect: &PixelRectBound| PixelRect {
                 left: rect.left - info.panel_position.left,
@@ -630,6 +1125,8 @@ impl YasScanner {
                 height: rect.bottom - rect.top,
             };
 
+            // the first dump that could not be written, which stops the scan
+            let mut dump_error: Option<String> = None;
             for i in rx {
                 let (capture, star) = match i {
                     Some(v) => v,
@@ -639,16 +1136,18 @@ impl YasScanner {
                 // capture.save("raw0.png");
                 // let now = SystemTime::now();
 
-                let model_inference = |pos: &PixelRectBound, name: &str, cnt: i32| -> String {
+                let mut samples: Vec<DatasetSample> = Vec::new();
+                let is_dataset_mode = dataset.is_some();
+                let mut model_inference = |pos: &PixelRectBound, name: &'static str, cnt: i32| -> String {
                     let raw_img = capture.crop_to_raw_img(&convert_rect(pos));
                     // raw_img.to_gray_image().save("raw.png");
                     // info!("raw_img: width = {}, height = {}", raw_img.w, raw_img.h);
 
                     if is_dump_mode {
-                        raw_img
-                            .grayscale_to_gray_image()
-                            .save(format!("dumps/{}_{}.png", name, cnt))
-                            .expect("Err");
+                        let path = format!("dumps/{}_{}.png", name, cnt);
+                        if let Err(e) = raw_img.grayscale_to_gray_image().save(&path) {
+                            dump_error.get_or_insert(format!("{}: {}", path, e));
+                        }
                     }
 
                     let processed_img = match pre_process(raw_img) {
@@ -658,16 +1157,27 @@ impl YasScanner {
                         }
                     };
                     if is_dump_mode {
-                        processed_img
-                            .to_gray_image()
-                            .save(format!("dumps/p_{}_{}.png", name, cnt))
-                            .expect("Err");
+                        let path = format!("dumps/p_{}_{}.png", name, cnt);
+                        if let Err(e) = processed_img.to_gray_image().save(&path) {
+                            dump_error.get_or_insert(format!("{}: {}", path, e));
+                        }
                     }
 
-                    let inference_result = model.inference_string(&processed_img);
+                    let (inference_result, confidence) =
+                        model.inference_string_with_confidence(&processed_img);
                     if is_dump_mode {
-                        fs::write(format!("dumps/{}_{}.txt", name, cnt), &inference_result)
-                            .expect("Err");
+                        let path = format!("dumps/{}_{}.txt", name, cnt);
+                        if let Err(e) = fs::write(&path, &inference_result) {
+                            dump_error.get_or_insert(format!("{}: {}", path, e));
+                        }
+                    }
+                    if is_dataset_mode {
+                        samples.push(DatasetSample {
+                            field: name,
+                            image: processed_img,
+                            prediction: inference_result.clone(),
+                            confidence,
+                        });
                     }
 
                     inference_result
@@ -686,6 +1196,9 @@ impl YasScanner {
 
                 let str_level = model_inference(&info.level_position, "level", cnt);
                 let str_equip = model_inference(&info.equip_position, "equip", cnt);
+                if let Some(e) = dump_error {
+                    return Err(ScanError::Io(format!("无法写入dumps：{}", e)));
+                }
 
                 cnt += 1;
 
@@ -710,50 +1223,85 @@ impl YasScanner {
                 // println!("{:?}", result);
                 let art = result.to_internal_artifact();
                 if let Some(a) = art {
+                    if let Some(ref k) = known {
+                        if k.contains(&a) {
+                            consecutive_known_count += 1;
+                        } else {
+                            consecutive_known_count = 0;
+                        }
+                    }
                     if hash.contains(&a) {
                         dup_count += 1;
+                        issue_count.fetch_add(1, Ordering::Relaxed);
                         consecutive_dup_count += 1;
                         warn!("dup artifact detected: {:?}", result);
                     } else {
                         consecutive_dup_count = 0;
+                        if let Some(ref mut d) = dataset {
+                            d.add(&a, samples);
+                        }
                         hash.insert(a.clone());
                         results.push(a);
                     }
                 } else {
                     error!("wrong detection: {:?}", result);
                     error_count += 1;
+                    issue_count.fetch_add(1, Ordering::Relaxed);
                     // println!("error parsing results");
                 }
                 if consecutive_dup_count >= info.art_row {
                     error!("检测到连续多个重复圣遗物，可能为翻页错误，或者为非背包顶部开始扫描");
                     break;
                 }
+                // a full row already exported, everything after it is older
+                if consecutive_known_count >= info.art_col {
+                    info!("reached artifacts of the previous export");
+                    reached_known_2.store(true, Ordering::Relaxed);
+                    break;
+                }
             }
 
             info!("error count: {}", error_count);
             info!("dup count: {}", dup_count);
 
-            if min_level > 0 {
+            if let Some(ref d) = dataset {
+                if let Err(e) = d.save() {
+                    error!("{}", e);
+                }
+            }
+
+            let artifacts = if min_level > 0 {
                 results
                     .into_iter()
                     .filter(|result| result.level >= min_level)
                     .collect::<Vec<_>>()
             } else {
                 results
-            }
+            };
+
+            Ok(ScanReport {
+                artifacts,
+                error_count,
+                dup_count,
+            })
         });
 
         let mut scanned_row = 0_u32;
         let mut scanned_count = 0_u32;
         let mut start_row = 0_u32;
+        let mut seen_issue_count = 0_u32;
+        // stops the scan, reported once the recognition thread is done
+        let mut failure: Option<ScanError> = None;
 
         self.move_to(0, 0);
         self.enigo.mouse_click(MouseButton::Left);
         utils::sleep(1000);
         // self.wait_until_switched();
-        self.sample_initial_color();
+        if let Err(e) = self.sample_initial_color() {
+            failure = Some(ScanError::Capture(e));
+        }
 
-        'outer: while scanned_count < count {
+        'outer: while failure.is_none() && scanned_count < count {
             'row: for row in start_row..self.row {
                 let c = if scanned_row == total_row - 1 {
                     last_row_col
@@ -771,19 +1319,54 @@ impl YasScanner {
                         break 'outer;
                     }
 
+                    if reached_known.load(Ordering::Relaxed) {
+                        break 'outer;
+                    }
+
                     self.move_to(row, col);
                     self.enigo.mouse_click(MouseButton::Left);
 
-                    self.wait_until_switched();
+                    match self.wait_until_switched() {
+                        Ok(true) => (),
+                        Ok(false) => self.timing.back_off(),
+                        Err(e) => {
+                            failure = Some(ScanError::Capture(e));
+                            break 'outer;
+                        }
+                    }
 
-                    let capture = self.capture_panel().unwrap();
-                    let star = self.get_star();
+                    // panel and star from the frame the switch was confirmed on
+                    let frame = match self.switch_detector.take_frame() {
+                        Some(v) => v,
+                        None => match self.grabber.grab_rect(&self.panel_area()) {
+                            Ok(v) => v,
+                            Err(e) => {
+                                failure = Some(ScanError::Capture(e));
+                                break 'outer;
+                            }
+                        },
+                    };
+                    let capture = self.capture_panel(&frame);
+                    let star = self.get_star(&frame);
                     if star < self.config.min_star {
+                        // sorted by acquisition order, lower stars are mixed in
+                        if is_incremental {
+                            scanned_count += 1;
+                            continue 'col;
+                        }
+                        break 'outer;
+                    }
+                    // the recognition thread has stopped
+                    if tx.send(Some((capture, star))).is_err() {
                         break 'outer;
                     }
-                    tx.send(Some((capture, star))).unwrap();
 
                     scanned_count += 1;
+                    let issue_count = self.issue_count.load(Ordering::Relaxed);
+                    if issue_count > seen_issue_count {
+                        seen_issue_count = issue_count;
+                        self.timing.back_off();
+                    }
                 } // end 'col
 
                 scanned_row += 1;
@@ -797,58 +1380,94 @@ impl YasScanner {
             let remain = count - scanned_count;
             let remain_row = (remain + self.col - 1) / self.col;
             let scroll_row = remain_row.min(self.row);
-            start_row = self.row - scroll_row;
             match self.scroll_rows(scroll_row) {
-                ScrollResult::TLE => {
+                Ok(ScrollResult::TLE) => {
                     error!("翻页出现问题");
+                    self.timing.back_off();
                     break 'outer;
                 }
-                ScrollResult::Interrupt => break 'outer,
-                _ => (),
+                Ok(ScrollResult::Interrupt) => break 'outer,
+                Ok(_) => (),
+                Err(e) => {
+                    failure = Some(ScanError::Capture(e));
+                    break 'outer;
+                }
+            }
+            // continue from the first row not scanned yet, wherever the scroll ended up
+            if scanned_row < self.visible_row || scanned_row >= self.visible_row + self.row {
+                error!(
+                    "翻页位置错误：应扫描第{}行，当前显示第{}行",
+                    scanned_row + 1,
+                    self.visible_row + 1
+                );
+                break 'outer;
             }
+            start_row = scanned_row - self.visible_row;
 
             utils::sleep(100);
         }
 
-        tx.send(None).unwrap();
+        // the recognition thread may have stopped early
+        let _ = tx.send(None);
 
         info!("扫描结束，等待识别线程结束，请勿关闭程序");
-        let results: Vec<InternalArtifact> = handle.join().unwrap();
-        info!("count: {}", results.len());
-        results
+        let report = match handle.join() {
+            Ok(v) => v,
+            Err(e) => {
+                let reason = match e.downcast_ref::<&str>() {
+                    Some(s) => String::from(*s),
+                    None => e.downcast_ref::<String>().cloned().unwrap_or_default(),
+                };
+                return Err(ScanError::Recognition(reason));
+            }
+        };
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        let mut report = report?;
+        if let Some(ref previous) = self.config.since {
+            let added = diff::diff(previous, &report.artifacts).added.len();
+            info!("相比上次导出新增{}个圣遗物", added);
+            report.artifacts = diff::merge(previous, report.artifacts);
+        }
+        assign_ids(&mut report.artifacts);
+        self.timing.save(&self.config.timing_profile);
+        info!("count: {}", report.artifacts.len());
+        Ok(report)
     }
 }
 
-impl YasScanner {
-    // pub fn start_from_scratch(config: YasScannerConfig) -> Result<Vec<InternalArtifact>, String> {
-    //     set_dpi_awareness();
-    //     let mut is_cloud = false;
-    //     let hwnd = match find_window_local() {
-    //         Ok(v) => {is_cloud = true; v},
-    //         Err(s) => {
-    //             match find_window_cloud() {
-    //                 Ok(v) => v,
-    //                 Err(s) => return Err(String::from("未找到原神窗口"))
-    //             }
-    //         }
-    //     };
-    //
-    //     show_window_and_set_foreground(hwnd);
-    //     sleep(1000);
-    //
-    //     let mut rect = match get_client_rect(hwnd) {
-    //         Ok(v) => v,
-    //         Err(_) => return Err(String::from("未能获取窗口大小"))
-    //     };
-    //
-    //     let info = match ScanInfo::from_rect(&rect) {
-    //         Ok(v) => v,
-    //         Err(e) => return Err(e)
-    //     };
-    //
-    //     let mut scanner = YasScanner::new(info, config, is_cloud);
-    //     let result = scanner.start();
-    //
-    //     Ok(result)
-    // }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn art_count_from_the_header() {
+        assert_eq!(parse_art_count("圣遗物 1234/1500"), Ok(Some(1234)));
+        assert_eq!(parse_art_count("圣遗物 7/1500"), Ok(Some(7)));
+        assert_eq!(parse_art_count("圣遗物 1a34/1500"), Ok(None));
+        assert_eq!(parse_art_count("武器 12/2000"), Ok(None));
+    }
+
+    fn matches(args: &[&str]) -> ArgMatches<'static> {
+        clap::App::new("yas")
+            .arg(clap::Arg::with_name("cloud").long("cloud"))
+            .arg(clap::Arg::with_name("no-cloud").long("no-cloud"))
+            .get_matches_from(std::iter::once("yas").chain(args.iter().cloned()))
+    }
+
+    #[test]
+    fn flags_override_the_config_file() {
+        assert!(flag(&matches(&["--cloud"]), "cloud", Some(false), false));
+        assert!(!flag(&matches(&["--no-cloud"]), "cloud", Some(true), false));
+        assert!(flag(&matches(&[]), "cloud", Some(true), false));
+        assert!(!flag(&matches(&[]), "cloud", None, false));
+    }
+
+    #[test]
+    fn short_art_count_is_an_error() {
+        assert!(parse_art_count("圣遗物12").is_err());
+        assert!(parse_art_count("圣遗物").is_err());
+    }
 }
+